@@ -0,0 +1,129 @@
+use crate::error::HaoleError;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration};
+
+pub const DEFAULT_PORT: u16 = 19132;
+
+const OFFLINE_MESSAGE_MAGIC: [u8; 16] = [
+    0x00, 0xFF, 0xFF, 0x00, 0xFE, 0xFE, 0xFE, 0xFE, 0xFD, 0xFD, 0xFD, 0xFD, 0x12, 0x34, 0x56, 0x78,
+];
+const UNCONNECTED_PING: u8 = 0x01;
+const UNCONNECTED_PONG: u8 = 0x1C;
+const ATTEMPTS: u32 = 3;
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub struct BedrockStatus {
+    pub edition: String,
+    pub motd: Vec<String>,
+    pub protocol: u32,
+    pub version: String,
+    pub online: u32,
+    pub max: u32,
+    pub server_guid: String,
+    pub game_mode: Option<String>,
+    pub latency: Duration,
+}
+
+fn ping_packet(client_guid: i64) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(33);
+    buf.push(UNCONNECTED_PING);
+    buf.extend_from_slice(&chrono::Utc::now().timestamp_millis().to_be_bytes());
+    buf.extend_from_slice(&OFFLINE_MESSAGE_MAGIC);
+    buf.extend_from_slice(&client_guid.to_be_bytes());
+    buf
+}
+
+fn pong_payload(packet: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    // ID(1) + 時刻(8) + サーバーGUID(8) + MAGIC(16) + 文字列長(2)
+    if packet.len() < 35 || packet[0] != UNCONNECTED_PONG {
+        return Err(HaoleError::parse("Unconnected Pong ではないパケットを受信しました。"));
+    }
+    if packet[17..33] != OFFLINE_MESSAGE_MAGIC {
+        return Err(HaoleError::parse("Unconnected Pong のマジックバイトが一致しません。"));
+    }
+    let len: usize = u16::from_be_bytes([packet[33], packet[34]]) as usize;
+    let body: &[u8] = packet.get(35..35 + len).ok_or_else(|| HaoleError::parse("Unconnected Pong の文字列が途中で切れています。"))?;
+    Ok(String::from_utf8_lossy(body).into_owned())
+}
+
+pub fn parse_pong(text: &str, latency: Duration) -> Result<BedrockStatus, Box<dyn std::error::Error>> {
+    let fields: Vec<&str> = text.split(';').collect();
+    if fields.len() < 6 {
+        return Err(HaoleError::parse(format!("Bedrock の応答のフィールド数が不足しています: {}", fields.len())));
+    }
+    let field = |i: usize| fields.get(i).map(|s: &&str| s.to_string()).filter(|s: &String| !s.is_empty());
+
+    Ok(BedrockStatus {
+        edition: fields[0].to_string(),
+        motd: [Some(fields[1].to_string()), field(7)].into_iter().flatten().collect(),
+        protocol: fields[2].parse()?,
+        version: fields[3].to_string(),
+        online: fields[4].parse()?,
+        max: fields[5].parse()?,
+        server_guid: field(6).unwrap_or_default(),
+        game_mode: field(8),
+        latency,
+    })
+}
+
+pub async fn status(host: &str, port: u16) -> Result<BedrockStatus, Box<dyn std::error::Error>> {
+    let socket: UdpSocket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect((host, port)).await?;
+    let client_guid: i64 = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+
+    let mut buf: [u8; 2048] = [0; 2048];
+    for _ in 0..ATTEMPTS {
+        let sent: Instant = Instant::now();
+        socket.send(&ping_packet(client_guid)).await?;
+        if let Ok(received) = timeout(ATTEMPT_TIMEOUT, socket.recv(&mut buf)).await {
+            let latency: Duration = sent.elapsed();
+            let text: String = pong_payload(&buf[..received?])?;
+            return parse_pong(&text, latency);
+        }
+    }
+
+    Err(std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!("{}:{} (Bedrock) から応答がありませんでした。", host, port),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pong_string() {
+        let st: BedrockStatus = parse_pong(
+            "MCPE;§aHavenMC;712;1.21.20;4;50;13253860892328930865;Survival Lobby;Survival;1;19132;19133;",
+            Duration::from_millis(12),
+        )
+        .unwrap();
+        assert_eq!(st.edition, "MCPE");
+        assert_eq!(st.motd, ["§aHavenMC", "Survival Lobby"]);
+        assert_eq!((st.protocol, st.version.as_str()), (712, "1.21.20"));
+        assert_eq!((st.online, st.max), (4, 50));
+        assert_eq!(st.server_guid, "13253860892328930865");
+        assert_eq!(st.game_mode.as_deref(), Some("Survival"));
+
+        let st: BedrockStatus = parse_pong("MCPE;Old;388;1.13.0;0;10", Duration::ZERO).unwrap();
+        assert_eq!(st.motd, ["Old"]);
+        assert_eq!(st.game_mode, None);
+    }
+
+    #[test]
+    fn extracts_pong_payload() {
+        let mut packet: Vec<u8> = vec![UNCONNECTED_PONG];
+        packet.extend_from_slice(&[0; 16]);
+        packet.extend_from_slice(&OFFLINE_MESSAGE_MAGIC);
+        packet.extend_from_slice(&4u16.to_be_bytes());
+        packet.extend_from_slice(b"MCPE");
+        assert_eq!(pong_payload(&packet).unwrap(), "MCPE");
+
+        packet[18] = 0;
+        assert!(pong_payload(&packet).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::error::HaoleError;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_SERVER: &str = "havenmc";
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edition {
+    #[default]
+    Java,
+    Bedrock,
+}
+
+// 省略した項目は HavenMC ではなく、一般的なサーバーの既定値になる
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServerProfile {
+    // 省略するとプロファイル名を使う
+    #[serde(default)]
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_bedrock_port")]
+    pub bedrock_port: u16,
+    #[serde(default)]
+    pub edition: Edition,
+    #[serde(default)]
+    pub api_url: Option<String>,
+    // 上から順に試す取得元
+    #[serde(default = "default_providers")]
+    pub providers: Vec<crate::provider::Source>,
+}
+
+fn default_port() -> u16 {
+    crate::slp::DEFAULT_PORT
+}
+
+fn default_bedrock_port() -> u16 {
+    crate::bedrock::DEFAULT_PORT
+}
+
+fn default_providers() -> Vec<crate::provider::Source> {
+    crate::provider::DEFAULT_ORDER.to_vec()
+}
+
+impl ServerProfile {
+    // 組み込みの havenmc プロファイル。HavenMC API を使うのはこのプロファイルだけ
+    pub fn havenmc() -> Self {
+        Self {
+            name: "HavenMC".into(),
+            host: "play.havenmc.jp".into(),
+            port: default_port(),
+            bedrock_port: default_bedrock_port(),
+            edition: Edition::Java,
+            api_url: Some("https://api.havenmc.jp/status".into()),
+            providers: default_providers(),
+        }
+    }
+
+    pub fn mcstatus_url(&self) -> String {
+        format!("https://api.mcstatus.io/v2/status/java/{}:{}", self.host, self.port)
+    }
+
+    fn with_address(mut self, addr: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (host, port) = match addr.rsplit_once(':') {
+            Some((host, port)) if (!host.contains(':') || host.starts_with('[')) && !port.ends_with(']') => {
+                (host.trim_matches(['[', ']']), Some(port.parse::<u16>().map_err(|_| HaoleError::config(format!("ポート番号が不正です: {}", port)))?))
+            }
+            _ => (addr.trim_matches(['[', ']']), None),
+        };
+        self.name = addr.to_string();
+        self.host = host.to_string();
+        if let Some(port) = port {
+            match self.edition {
+                Edition::Java => self.port = port,
+                Edition::Bedrock => self.bedrock_port = port,
+            }
+        }
+        // 別のサーバーを指しているため HavenMC API の結果は使わない
+        self.api_url = None;
+        Ok(self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct HaoleConfig {
+    pub mode: String,
+    pub default_server: String,
+    pub servers: BTreeMap<String, ServerProfile>,
+    pub friends: Vec<String>,
+    pub friend_hook: Option<String>,
+}
+
+impl Default for HaoleConfig {
+    fn default() -> Self {
+        Self {
+            mode: "cli".into(),
+            default_server: DEFAULT_SERVER.into(),
+            servers: BTreeMap::from([(DEFAULT_SERVER.to_string(), ServerProfile::havenmc())]),
+            friends: Vec::new(),
+            friend_hook: None,
+        }
+    }
+}
+
+impl HaoleConfig {
+    pub fn load() -> Result<Self, confy::ConfyError> {
+        confy::load("haole", "config")
+    }
+
+    pub fn store(&self) -> Result<(), confy::ConfyError> {
+        confy::store("haole", "config", self)
+    }
+
+    pub fn resolve(&self, server: Option<&str>, host: Option<&str>) -> Result<ServerProfile, Box<dyn std::error::Error>> {
+        let name: &str = server.unwrap_or(&self.default_server);
+        let mut profile: ServerProfile = match self.servers.get(name) {
+            Some(p) => p.clone(),
+            None if server.is_none() && name == DEFAULT_SERVER => ServerProfile::havenmc(),
+            None => return Err(HaoleError::config(format!("サーバープロファイル '{}' が設定ファイルに見つかりません。", name))),
+        };
+        if profile.name.is_empty() {
+            profile.name = name.to_string();
+        }
+        match host {
+            Some(addr) => profile.with_address(addr),
+            None => Ok(profile),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_profile_does_not_inherit_havenmc() {
+        let cfg: HaoleConfig = serde_json::from_str(r#"{ "servers": { "staging": { "host": "staging.example.com", "port": 25566 } } }"#).unwrap();
+        let profile: ServerProfile = cfg.resolve(Some("staging"), None).unwrap();
+        assert_eq!((profile.name.as_str(), profile.host.as_str(), profile.port), ("staging", "staging.example.com", 25566));
+        assert_eq!(profile.bedrock_port, crate::bedrock::DEFAULT_PORT);
+        assert_eq!(profile.edition, Edition::Java);
+        assert_eq!(profile.api_url, None);
+        assert_eq!(profile.providers, crate::provider::DEFAULT_ORDER);
+
+        let missing_host: Result<ServerProfile, serde_json::Error> = serde_json::from_str(r#"{ "name": "No Host" }"#);
+        assert!(missing_host.is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::fmt;
+
+pub const EXIT_OK: u8 = 0;
+pub const EXIT_FALSE: u8 = 1;
+pub const EXIT_NETWORK: u8 = 2;
+pub const EXIT_PARSE: u8 = 3;
+pub const EXIT_CONFIG: u8 = 4;
+pub const EXIT_IO: u8 = 5;
+
+#[derive(Debug)]
+pub enum HaoleError {
+    Config(String),
+    Parse(String),
+}
+
+impl HaoleError {
+    pub fn config(msg: impl Into<String>) -> Box<dyn Error> {
+        Box::new(HaoleError::Config(msg.into()))
+    }
+
+    pub fn parse(msg: impl Into<String>) -> Box<dyn Error> {
+        Box::new(HaoleError::Parse(msg.into()))
+    }
+}
+
+impl fmt::Display for HaoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaoleError::Config(msg) | HaoleError::Parse(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl Error for HaoleError {}
+
+fn classify(err: &(dyn Error + 'static)) -> Option<u8> {
+    if let Some(e) = err.downcast_ref::<HaoleError>() {
+        return Some(match e {
+            HaoleError::Config(_) => EXIT_CONFIG,
+            HaoleError::Parse(_) => EXIT_PARSE,
+        });
+    }
+    if err.is::<confy::ConfyError>() || err.is::<clap::Error>() {
+        return Some(EXIT_CONFIG);
+    }
+    if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+        return Some(if e.is_decode() { EXIT_PARSE } else { EXIT_NETWORK });
+    }
+    if err.is::<serde_json::Error>()
+        || err.is::<std::num::ParseIntError>()
+        || err.is::<std::string::FromUtf16Error>()
+        || err.is::<std::string::FromUtf8Error>()
+    {
+        return Some(EXIT_PARSE);
+    }
+    if let Some(e) = err.downcast_ref::<std::io::Error>() {
+        return Some(if is_network_io(e) { EXIT_NETWORK } else { EXIT_IO });
+    }
+    None
+}
+
+// ソケットや接続のエラーだけをネットワークエラーとし、ファイルの読み書きなどのローカルの失敗と区別する
+fn is_network_io(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::NotConnected
+            | std::io::ErrorKind::AddrInUse
+            | std::io::ErrorKind::AddrNotAvailable
+            | std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::TimedOut
+            | std::io::ErrorKind::UnexpectedEof
+            | std::io::ErrorKind::HostUnreachable
+            | std::io::ErrorKind::NetworkUnreachable
+            | std::io::ErrorKind::NetworkDown
+    ) || is_dns_failure(e)
+}
+
+// 名前解決の失敗は専用のエラー型や種類がないため、メッセージで見分ける
+pub fn is_dns_failure(err: &dyn Error) -> bool {
+    let message: String = err.to_string().to_lowercase();
+    message.contains("lookup address") || message.contains("dns error")
+}
+
+pub fn exit_code(err: &(dyn Error + 'static)) -> u8 {
+    let mut current: Option<&(dyn Error + 'static)> = Some(err);
+    while let Some(e) = current {
+        if let Some(code) = classify(e) {
+            return code;
+        }
+        current = e.source();
+    }
+    EXIT_NETWORK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_network_and_local_io() {
+        let code = |e: std::io::Error| -> u8 { exit_code(&e) };
+        assert_eq!(code(std::io::Error::from(std::io::ErrorKind::ConnectionRefused)), EXIT_NETWORK);
+        assert_eq!(code(std::io::Error::from(std::io::ErrorKind::TimedOut)), EXIT_NETWORK);
+        assert_eq!(code(std::io::Error::other("failed to lookup address information: Name or service not known")), EXIT_NETWORK);
+        assert!(is_dns_failure(&std::io::Error::other("client error (Connect): dns error: failed to lookup address information")));
+        assert_eq!(code(std::io::Error::from(std::io::ErrorKind::PermissionDenied)), EXIT_IO);
+        assert_eq!(code(std::io::Error::from(std::io::ErrorKind::NotFound)), EXIT_IO);
+        assert_eq!(exit_code(HaoleError::parse("bad").as_ref()), EXIT_PARSE);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::ServerProfile;
+use crate::history::{Sample, Since, Store};
+use crate::output::{self, OutputFormat};
+use chrono::{DateTime, Local};
+use colored::*;
+use serde_json::json;
+use tokio::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EventKind {
+    Join,
+    Leave,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerEvent {
+    pub time: DateTime<Local>,
+    pub kind: EventKind,
+    pub name: String,
+}
+
+impl PlayerEvent {
+    pub fn marker(&self) -> char {
+        match self.kind {
+            EventKind::Join => '+',
+            EventKind::Leave => '-',
+        }
+    }
+
+    pub fn line(&self) -> String {
+        format!("[{}] {} {}", self.time.format("%H:%M:%S"), self.marker(), self.name)
+    }
+
+    pub fn colored_line(&self) -> String {
+        let text: String = format!("{} {}", self.marker(), self.name);
+        let text: ColoredString = match self.kind {
+            EventKind::Join => text.green(),
+            EventKind::Leave => text.red(),
+        };
+        format!("[{}] {}", self.time.format("%H:%M:%S"), text)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "time": self.time.to_rfc3339(),
+            "event": match self.kind { EventKind::Join => "join", EventKind::Leave => "leave" },
+            "player": self.name,
+        })
+    }
+}
+
+pub fn diff(previous: &[String], current: &[String], time: DateTime<Local>) -> Vec<PlayerEvent> {
+    let joined = current.iter().filter(|p: &&String| !previous.contains(p)).map(|p: &String| PlayerEvent { time, kind: EventKind::Join, name: p.clone() });
+    let left = previous.iter().filter(|p: &&String| !current.contains(p)).map(|p: &String| PlayerEvent { time, kind: EventKind::Leave, name: p.clone() });
+    joined.chain(left).collect()
+}
+
+// 直前のプレイヤー一覧を覚えておき、次の一覧との差分をイベントとして返す
+#[derive(Default)]
+pub struct EventTracker {
+    previous: Option<Vec<String>>,
+}
+
+impl EventTracker {
+    pub fn update(&mut self, sample: &Sample) -> Vec<PlayerEvent> {
+        // 一覧が分からないときは比較せず、前回の一覧を保持する
+        let Some(current) = sample.known_players() else {
+            return Vec::new();
+        };
+        let events: Vec<PlayerEvent> = match &self.previous {
+            Some(previous) => diff(previous, current, sample.time),
+            None => Vec::new(),
+        };
+        self.previous = Some(current.to_vec());
+        events
+    }
+}
+
+pub fn from_history(samples: &[Sample]) -> Vec<PlayerEvent> {
+    let mut tracker: EventTracker = EventTracker::default();
+    samples.iter().flat_map(|s: &Sample| tracker.update(s)).collect()
+}
+
+fn print_event(target: &ServerProfile, event: &PlayerEvent, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    if format.is_structured() {
+        output::emit(format, "event", &target.name, event.to_json())
+    } else {
+        println!("{}", event.colored_line());
+        Ok(())
+    }
+}
+
+pub async fn run(target: &ServerProfile, since: &Since, follow: bool, interval: Duration, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let store: Store = Store::open(&target.name)?;
+    let samples: Vec<Sample> = store.load(Some(since.start()))?;
+    let events: Vec<PlayerEvent> = from_history(&samples);
+
+    if format.is_structured() && !follow {
+        return output::emit(format, "events", &target.name, json!({
+            "since": since.label,
+            "events": events.iter().map(PlayerEvent::to_json).collect::<Vec<serde_json::Value>>(),
+        }));
+    }
+    if events.is_empty() && !format.is_structured() {
+        println!("{} 直近 {} に参加・退出の記録はありません。", "!!".yellow(), since.label);
+    }
+    for event in &events {
+        print_event(target, event, format)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    if !format.is_structured() {
+        println!("\n{} {}秒おきに監視中... (Ctrl+Cで終了)", ">>".blue(), interval.as_secs());
+    }
+    let mut tracker: EventTracker = EventTracker::default();
+    if let Some(last) = samples.last() {
+        tracker.update(last);
+    }
+    loop {
+        let (_, sample) = crate::record_history(target, &store).await;
+        for event in tracker.update(&sample) {
+            print_event(target, &event, format)?;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_player_lists() {
+        let prev: Vec<String> = vec!["Steve".into(), "Alex".into()];
+        let next: Vec<String> = vec!["Alex".into(), "Notch".into()];
+        let events: Vec<PlayerEvent> = diff(&prev, &next, Local::now());
+        let summary: Vec<(char, &str)> = events.iter().map(|e: &PlayerEvent| (e.marker(), e.name.as_str())).collect();
+        assert_eq!(summary, [('+', "Notch"), ('-', "Steve")]);
+    }
+
+    #[test]
+    fn ignores_truncated_samples() {
+        let mut tracker: EventTracker = EventTracker::default();
+        assert!(tracker.update(&Sample::at(1, 12, 0).with_players(&["Steve", "Alex"])).is_empty());
+        // 20人いるうち、無作為に選ばれた一部だけが返ってきた
+        assert!(tracker.update(&Sample::at(1, 12, 1).with_players(&["Notch", "Herobrine"]).with_count(20)).is_empty());
+        assert!(tracker.update(&Sample::at(1, 12, 2).with_players(&["Alex"]).with_count(20)).is_empty());
+        let events: Vec<PlayerEvent> = tracker.update(&Sample::at(1, 12, 3).with_players(&["Alex", "Notch"]));
+        let summary: Vec<(char, &str)> = events.iter().map(|e: &PlayerEvent| (e.marker(), e.name.as_str())).collect();
+        assert_eq!(summary, [('+', "Notch"), ('-', "Steve")]);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::config::ServerProfile;
+use crate::history::{Sample, Store};
+use crate::provider::Source;
+use colored::*;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Duration;
+
+const SOURCES: [&str; 3] = ["haven_api", "mcstatus", "slp"];
+
+type Gauge = (&'static str, &'static str, fn(&SourceMetrics) -> Option<f64>);
+
+#[derive(Default)]
+struct SourceMetrics {
+    up: Option<f64>,
+    players_online: Option<f64>,
+    players_max: Option<f64>,
+    protocol_version: Option<f64>,
+    ping_seconds: Option<f64>,
+    scrape_errors: u64,
+}
+
+struct Registry {
+    server: String,
+    sources: BTreeMap<&'static str, SourceMetrics>,
+}
+
+impl Registry {
+    fn new(server: &str) -> Self {
+        Self {
+            server: server.to_string(),
+            sources: SOURCES.iter().map(|s: &&str| (*s, SourceMetrics::default())).collect(),
+        }
+    }
+
+    fn record<T>(&mut self, source: &'static str, result: &Result<T, Box<dyn std::error::Error>>, apply: impl FnOnce(&mut SourceMetrics, &T)) {
+        let m: &mut SourceMetrics = self.sources.entry(source).or_default();
+        match result {
+            Ok(value) => {
+                // 前回の値が残らないよう、取得できた項目だけを出力する
+                *m = SourceMetrics { scrape_errors: m.scrape_errors, ..SourceMetrics::default() };
+                apply(m, value);
+            }
+            Err(_) => {
+                m.scrape_errors += 1;
+                *m = SourceMetrics { up: Some(0.0), scrape_errors: m.scrape_errors, ..SourceMetrics::default() };
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let gauges: [Gauge; 5] = [
+            ("haole_server_up", "サーバーがオンラインなら1、オフラインまたは取得失敗なら0", |m| m.up),
+            ("haole_players_online", "オンラインのプレイヤー数", |m| m.players_online),
+            ("haole_players_max", "最大プレイヤー数", |m| m.players_max),
+            ("haole_protocol_version", "サーバーのプロトコルバージョン", |m| m.protocol_version),
+            ("haole_ping_seconds", "Server List Ping の往復時間 (秒)", |m| m.ping_seconds),
+        ];
+
+        let mut out: String = String::new();
+        for (name, help, get) in gauges {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            for (source, m) in &self.sources {
+                if let Some(value) = get(m) {
+                    let _ = writeln!(out, "{}{{server=\"{}\",source=\"{}\"}} {}", name, escape(&self.server), source, value);
+                }
+            }
+        }
+        let _ = writeln!(out, "# HELP haole_scrape_errors_total 取得に失敗した回数");
+        let _ = writeln!(out, "# TYPE haole_scrape_errors_total counter");
+        for (source, m) in &self.sources {
+            let _ = writeln!(out, "haole_scrape_errors_total{{server=\"{}\",source=\"{}\"}} {}", escape(&self.server), source, m.scrape_errors);
+        }
+        out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+async fn poll(target: &ServerProfile, registry: &mut Registry, store: &Store) {
+    let (haven, mcstatus, slp) = tokio::join!(
+        crate::fetch_haven_status(target),
+        crate::fetch_haven_status_by_mcstatusio(target),
+        crate::query_server(&target.host, target.port),
+    );
+
+    if target.api_url.is_some() {
+        registry.record("haven_api", &haven, |m: &mut SourceMetrics, st: &crate::HavenStatus| {
+            m.up = Some(if st.online { 1.0 } else { 0.0 });
+            m.players_online = Some(st.players.online as f64);
+            m.players_max = Some(st.players.max as f64);
+        });
+    }
+    registry.record("mcstatus", &mcstatus, |m: &mut SourceMetrics, st: &crate::McStatusIOResponse| {
+        m.up = Some(if st.online { 1.0 } else { 0.0 });
+        // オフラインのときは人数やバージョンが返ってこない
+        if st.online {
+            if let Some(players) = &st.players {
+                m.players_online = Some(players.online as f64);
+                m.players_max = Some(players.max as f64);
+            }
+            m.protocol_version = Some(st.version.protocol as f64);
+        }
+    });
+    registry.record("slp", &slp, |m: &mut SourceMetrics, resp: &crate::slp::SlpResponse| {
+        m.up = Some(1.0);
+        if let Some(players) = &resp.status.players {
+            m.players_online = Some(players.online as f64);
+            m.players_max = Some(players.max as f64);
+        }
+        m.protocol_version = Some(resp.status.version.protocol as f64);
+        m.ping_seconds = Some(resp.latency.as_secs_f64());
+    });
+
+    let latency: Option<Duration> = slp.as_ref().ok().map(|resp: &crate::slp::SlpResponse| resp.latency);
+    let sample: Sample = match (haven, slp) {
+        (Ok(st), _) if target.api_url.is_some() => Sample::from_status(&target.name, &st, Source::HavenApi, latency),
+        (_, Ok(resp)) => Sample::from_status(&target.name, &crate::HavenStatus::from_slp(&resp), Source::Slp, latency),
+        // 失敗した取得元を試した順に並べ、他のコマンドと同じく最初の取得元を原因として記録する
+        (haven, Err(e)) => {
+            let mut attempts: Vec<(Source, Box<dyn std::error::Error>)> = Vec::new();
+            if let (Some(_), Err(haven_err)) = (&target.api_url, haven) {
+                attempts.push((Source::HavenApi, haven_err));
+            }
+            attempts.push((Source::Slp, e));
+            Sample::failed(&target.name, &crate::provider::Failure { attempts })
+        }
+    };
+    let _ = store.append(&sample);
+}
+
+async fn serve(mut stream: TcpStream, metrics: String) {
+    let mut buf: [u8; 1024] = [0; 1024];
+    let n: usize = stream.read(&mut buf).await.unwrap_or(0);
+    let request: std::borrow::Cow<'_, str> = String::from_utf8_lossy(&buf[..n]);
+    let path: &str = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, content_type, body) = match path {
+        "/metrics" => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", metrics),
+        "/" => ("200 OK", "text/html; charset=utf-8", "<html><body><a href=\"/metrics\">/metrics</a></body></html>".to_string()),
+        _ => ("404 Not Found", "text/plain; charset=utf-8", "not found\n".to_string()),
+    };
+    let response: String = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+pub async fn run(target: &ServerProfile, listen: SocketAddr, interval: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let listener: TcpListener = TcpListener::bind(listen).await?;
+    println!("{} http://{}/metrics で待ち受け中... ({}秒おきに更新, Ctrl+Cで終了)", ">>".blue(), listen, interval.as_secs());
+
+    let mut registry: Registry = Registry::new(&target.name);
+    let store: Store = Store::open(&target.name)?;
+    let mut metrics: String = registry.render();
+    loop {
+        // 取得中もスクレイプに応答できるよう、取得と accept を並行して待つ
+        {
+            let polling = poll(target, &mut registry, &store);
+            tokio::pin!(polling);
+            loop {
+                tokio::select! {
+                    _ = &mut polling => break,
+                    conn = listener.accept() => {
+                        if let Ok((stream, _)) = conn {
+                            tokio::spawn(serve(stream, metrics.clone()));
+                        }
+                    }
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
+            }
+        }
+        metrics = registry.render();
+
+        let sleep = tokio::time::sleep(interval);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                conn = listener.accept() => {
+                    if let Ok((stream, _)) = conn {
+                        tokio::spawn(serve(stream, metrics.clone()));
+                    }
+                }
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(players: u32) -> Result<u32, Box<dyn std::error::Error>> {
+        Ok(players)
+    }
+
+    fn players(m: &mut SourceMetrics, online: &u32) {
+        m.up = Some(1.0);
+        m.players_online = Some(*online as f64);
+        m.ping_seconds = Some(0.05);
+    }
+
+    #[test]
+    fn record_clears_stale_values() {
+        let mut registry: Registry = Registry::new("test");
+        registry.record("slp", &ok(3), players);
+        let m: &SourceMetrics = &registry.sources["slp"];
+        assert_eq!((m.up, m.players_online, m.scrape_errors), (Some(1.0), Some(3.0), 0));
+
+        // 失敗したら up=0 にして、前回の人数などは出力しない
+        registry.record("slp", &Err("timeout".into()), players);
+        registry.record("slp", &Err("timeout".into()), players);
+        let m: &SourceMetrics = &registry.sources["slp"];
+        assert_eq!((m.up, m.players_online, m.ping_seconds, m.scrape_errors), (Some(0.0), None, None, 2));
+
+        // 成功しても失敗回数は累計のまま
+        registry.record("slp", &ok(1), |m: &mut SourceMetrics, online: &u32| m.players_online = Some(*online as f64));
+        let m: &SourceMetrics = &registry.sources["slp"];
+        assert_eq!((m.up, m.players_online, m.scrape_errors), (None, Some(1.0), 2));
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let mut registry: Registry = Registry::new("Haven \"MC\"\\\n");
+        registry.record("slp", &ok(3), players);
+        registry.record("mcstatus", &Err("timeout".into()), players);
+        let text: String = registry.render();
+        let label: &str = r#"server="Haven \"MC\"\\\n""#;
+
+        assert!(text.contains("# HELP haole_server_up サーバーがオンラインなら1、オフラインまたは取得失敗なら0\n# TYPE haole_server_up gauge\n"));
+        assert!(text.contains("# TYPE haole_scrape_errors_total counter\n"));
+        assert!(text.contains(&format!("haole_server_up{{{},source=\"mcstatus\"}} 0\n", label)));
+        assert!(text.contains(&format!("haole_server_up{{{},source=\"slp\"}} 1\n", label)));
+        assert!(text.contains(&format!("haole_players_online{{{},source=\"slp\"}} 3\n", label)));
+        assert!(text.contains(&format!("haole_ping_seconds{{{},source=\"slp\"}} 0.05\n", label)));
+        // 取得していない値の行は出さないが、失敗回数は取得元ごとに必ず出す
+        assert!(!text.contains(&format!("haole_server_up{{{},source=\"haven_api\"}}", label)));
+        assert!(!text.contains(&format!("haole_players_online{{{},source=\"mcstatus\"}}", label)));
+        assert!(text.contains(&format!("haole_scrape_errors_total{{{},source=\"haven_api\"}} 0\n", label)));
+        assert!(text.contains(&format!("haole_scrape_errors_total{{{},source=\"mcstatus\"}} 1\n", label)));
+    }
+}
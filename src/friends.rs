@@ -0,0 +1,128 @@
+use crate::config::ServerProfile;
+use crate::history::{Sample, Store};
+use crate::output::{self, OutputFormat};
+use colored::*;
+use serde_json::json;
+use tokio::time::Duration;
+
+// MCID は大文字小文字を区別しない
+pub fn is_friend(friends: &[String], name: &str) -> bool {
+    friends.iter().any(|f: &String| f.eq_ignore_ascii_case(name))
+}
+
+// フレンドを先頭に寄せる (それぞれの中の順序は保つ)
+pub fn sort_friends_first(friends: &[String], players: &mut [String]) {
+    players.sort_by_key(|p: &String| !is_friend(friends, p));
+}
+
+// 通知したフレンドを覚えておき、本当に退出するまで再通知しない
+#[derive(Default)]
+pub struct FriendTracker {
+    announced: Vec<String>,
+}
+
+impl FriendTracker {
+    // 新たにオンラインになったフレンドを返す (起動時にすでにオンラインのフレンドも含む)
+    pub fn update(&mut self, friends: &[String], sample: &Sample) -> Vec<String> {
+        let visible: &[String] = if sample.online { &sample.players } else { &[] };
+        // 一覧は一部の名前だけのことがあるため、見えないフレンドを退出したとみなすのは一覧がそろっているときだけ
+        if !sample.online || sample.known_players().is_some() {
+            self.announced.retain(|f: &String| visible.contains(f));
+        }
+        let joined: Vec<String> = visible
+            .iter()
+            .filter(|p: &&String| is_friend(friends, p) && !self.announced.contains(p))
+            .cloned()
+            .collect();
+        self.announced.extend(joined.iter().cloned());
+        joined
+    }
+}
+
+fn run_hook(hook: &str, target: &ServerProfile, name: &str) {
+    let mut cmd: tokio::process::Command = if cfg!(windows) {
+        let mut c: tokio::process::Command = tokio::process::Command::new("cmd");
+        c.arg("/C").arg(hook);
+        c
+    } else {
+        let mut c: tokio::process::Command = tokio::process::Command::new("sh");
+        c.arg("-c").arg(hook);
+        c
+    };
+    cmd.env("HAOLE_FRIEND", name).env("HAOLE_SERVER", &target.name);
+    match cmd.spawn() {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                let _ = child.wait().await;
+            });
+        }
+        Err(e) => eprintln!("{} フック コマンドの実行に失敗しました: {}", "!!".yellow(), e),
+    }
+}
+
+fn alert(target: &ServerProfile, name: &str, hook: Option<&str>, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    if format.is_structured() {
+        output::emit(format, "friend", &target.name, json!({
+            "time": chrono::Local::now().to_rfc3339(),
+            "player": name,
+        }))?;
+    } else if format != OutputFormat::Quiet {
+        println!("\x07[{}] {} {} がオンラインになりました", chrono::Local::now().format("%H:%M:%S"), "★".yellow(), name.black().on_yellow().bold());
+    }
+    if let Some(hook) = hook {
+        run_hook(hook, target, name);
+    }
+    Ok(())
+}
+
+pub async fn watch(target: &ServerProfile, friends: &[String], hook: Option<&str>, interval: Duration, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    if !format.is_structured() && format != OutputFormat::Quiet {
+        println!("{} {} 人のフレンドを {}秒おきに監視中... (Ctrl+Cで終了)", ">>".blue(), friends.len(), interval.as_secs());
+    }
+    let store: Store = Store::open(&target.name)?;
+    let mut tracker: FriendTracker = FriendTracker::default();
+    loop {
+        let (_, sample) = crate::record_history(target, &store).await;
+        for name in tracker.update(friends, &sample) {
+            alert(target, &name, hook, format)?;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p: &&str| p.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_and_sorts_friends() {
+        let friends: Vec<String> = names(&["steve", "Notch"]);
+        assert!(is_friend(&friends, "Steve"));
+        assert!(!is_friend(&friends, "Alex"));
+
+        let mut players: Vec<String> = names(&["Alex", "Notch", "Herobrine", "STEVE"]);
+        sort_friends_first(&friends, &mut players);
+        assert_eq!(players, ["Notch", "STEVE", "Alex", "Herobrine"]);
+    }
+
+    #[test]
+    fn announces_once_until_really_left() {
+        let friends: Vec<String> = names(&["Steve"]);
+        let mut tracker: FriendTracker = FriendTracker::default();
+        let truncated = |players: &[&str]| -> Sample { Sample::at(1, 12, 0).with_players(players).with_count(20) };
+        assert_eq!(tracker.update(&friends, &truncated(&["Steve", "Alex"])), ["Steve"]);
+        // 一部の名前だけの一覧で見えなくなっても、退出したとはみなさない
+        assert!(tracker.update(&friends, &truncated(&["Alex"])).is_empty());
+        assert!(tracker.update(&friends, &truncated(&["Steve"])).is_empty());
+        // そろった一覧にいなければ退出とみなし、次に見えたら再び通知する
+        assert!(tracker.update(&friends, &Sample::at(1, 12, 0).with_players(&["Alex"])).is_empty());
+        assert_eq!(tracker.update(&friends, &truncated(&["Steve"])), ["Steve"]);
+    }
+}
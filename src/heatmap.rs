@@ -0,0 +1,178 @@
+use crate::config::ServerProfile;
+use crate::history::{self, Sample, Store};
+use crate::output::{self, OutputFormat};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use colored::*;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use serde_json::json;
+
+pub const DEFAULT_WEEKS: u32 = 4;
+// 10年分まで。これより長いと現在時刻から引けなくなる
+pub const MAX_WEEKS: u32 = 520;
+const DAYS: [(&str, &str); 7] = [("mon", "月"), ("tue", "火"), ("wed", "水"), ("thu", "木"), ("fri", "金"), ("sat", "土"), ("sun", "日")];
+// 少ない順に5段階で塗り分ける
+const LEVELS: [Color; 5] = [Color::Blue, Color::Cyan, Color::Green, Color::Yellow, Color::Red];
+
+// 曜日 (月曜始まり) × 時 ごとの、オンライン中のプレイヤー数の合計と記録数
+#[derive(Default)]
+pub struct Heatmap {
+    cells: [[(u64, u64); 24]; 7],
+}
+
+impl Heatmap {
+    pub fn from_samples(samples: &[Sample]) -> Self {
+        let mut heatmap: Self = Self::default();
+        for sample in samples {
+            heatmap.add(sample);
+        }
+        heatmap
+    }
+
+    pub fn add(&mut self, sample: &Sample) {
+        if !sample.online {
+            return;
+        }
+        let cell: &mut (u64, u64) = &mut self.cells[sample.time.weekday().num_days_from_monday() as usize][sample.time.hour() as usize];
+        cell.0 += sample.players_online as u64;
+        cell.1 += 1;
+    }
+
+    pub fn average(&self, day: usize, hour: usize) -> Option<f64> {
+        let (sum, n) = self.cells[day][hour];
+        (n > 0).then(|| sum as f64 / n as f64)
+    }
+
+    pub fn max(&self) -> f64 {
+        (0..7).flat_map(|d: usize| (0..24).filter_map(move |h: usize| self.average(d, h))).fold(0.0, f64::max)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().flatten().all(|(_, n)| *n == 0)
+    }
+
+    fn level(&self, value: f64) -> usize {
+        let max: f64 = self.max();
+        if max <= 0.0 {
+            return 0;
+        }
+        ((value / max * LEVELS.len() as f64) as usize).min(LEVELS.len() - 1)
+    }
+
+    fn header() -> String {
+        (0..24).step_by(3).map(|h: usize| format!("{:<6}", h)).collect::<String>().trim_end().to_string()
+    }
+
+    fn legend(&self) -> Vec<(Color, String)> {
+        let step: f64 = self.max() / LEVELS.len() as f64;
+        LEVELS.iter().enumerate().map(|(i, c)| (*c, format!("{:.1}-{:.1}", step * i as f64, step * (i + 1) as f64))).collect()
+    }
+
+    pub fn to_lines(&self) -> Vec<Line<'static>> {
+        let mut lines: Vec<Line<'static>> = vec![Line::from(Span::styled(format!("    {}", Self::header()), Style::default().fg(Color::DarkGray)))];
+        for (day, (_, label)) in DAYS.iter().enumerate() {
+            let mut spans: Vec<Span<'static>> = vec![Span::raw(format!(" {} ", label))];
+            spans.extend((0..24).map(|hour: usize| match self.average(day, hour) {
+                Some(v) => Span::styled("██", Style::default().fg(LEVELS[self.level(v)])),
+                None => Span::styled("··", Style::default().fg(Color::DarkGray)),
+            }));
+            lines.push(Line::from(spans));
+        }
+        let mut legend: Vec<Span<'static>> = vec![Span::raw("    "), Span::styled("··", Style::default().fg(Color::DarkGray)), Span::raw(" 記録なし ")];
+        for (color, range) in self.legend() {
+            legend.push(Span::styled(" ██", Style::default().fg(color)));
+            legend.push(Span::raw(format!(" {}", range)));
+        }
+        lines.push(Line::default());
+        lines.push(Line::from(legend));
+        lines
+    }
+
+    pub fn to_ansi(&self) -> String {
+        let paint = |text: &str, color: Color| -> ColoredString {
+            match color {
+                Color::Blue => text.blue(),
+                Color::Cyan => text.cyan(),
+                Color::Green => text.green(),
+                Color::Yellow => text.yellow(),
+                Color::Red => text.red(),
+                _ => text.bright_black(),
+            }
+        };
+        let mut out: String = format!("    {}\n", Self::header().bright_black());
+        for (day, (_, label)) in DAYS.iter().enumerate() {
+            out.push_str(&format!(" {} ", label));
+            for hour in 0..24 {
+                match self.average(day, hour) {
+                    Some(v) => out.push_str(&paint("██", LEVELS[self.level(v)]).to_string()),
+                    None => out.push_str(&"··".bright_black().to_string()),
+                }
+            }
+            out.push('\n');
+        }
+        out.push_str(&format!("\n    {} 記録なし ", "··".bright_black()));
+        for (color, range) in self.legend() {
+            out.push_str(&format!(" {} {}", paint("██", color), range));
+        }
+        out.push('\n');
+        out
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let days: Vec<serde_json::Value> = DAYS
+            .iter()
+            .enumerate()
+            .map(|(day, (key, _))| json!({ "day": key, "hours": (0..24).map(|h: usize| self.average(day, h)).collect::<Vec<_>>() }))
+            .collect();
+        json!(days)
+    }
+}
+
+pub fn run(target: &ServerProfile, weeks: u32, format: OutputFormat) -> Result<bool, Box<dyn std::error::Error>> {
+    let store: Store = Store::open(&target.name)?;
+    let start: DateTime<Local> = Local::now() - chrono::Duration::weeks(weeks as i64);
+    let samples: Vec<Sample> = store.load(Some(start))?;
+    let heatmap: Heatmap = Heatmap::from_samples(&samples);
+
+    if format.is_structured() {
+        output::emit(format, "heatmap", &target.name, json!({
+            "weeks": weeks,
+            "samples": samples.len(),
+            "max": heatmap.max(),
+            "days": heatmap.to_json(),
+        }))?;
+        return Ok(!heatmap.is_empty());
+    }
+
+    if heatmap.is_empty() {
+        history::print_no_records(&format!("{}週間", weeks));
+        return Ok(false);
+    }
+
+    println!("{} 直近 {} 週間の曜日・時間帯別の平均プレイヤー数 (最大 {:.1} 人)\n", ">>".blue(), weeks, heatmap.max());
+    print!("{}", heatmap.to_ansi());
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::incidents::Cause;
+
+    #[test]
+    fn averages_by_weekday_and_hour() {
+        // 2026-01-05 は月曜日
+        let heatmap: Heatmap = Heatmap::from_samples(&[
+            Sample::at(5, 20, 0).with_count(4),
+            Sample::at(12, 20, 0).with_count(8),
+            Sample::at(12, 20, 0).down(Cause::Unknown),
+            Sample::at(10, 9, 0).with_count(1),
+        ]);
+        assert_eq!(heatmap.average(0, 20), Some(6.0));
+        assert_eq!(heatmap.average(5, 9), Some(1.0));
+        assert_eq!(heatmap.average(6, 9), None);
+        assert_eq!(heatmap.max(), 6.0);
+        assert_eq!(heatmap.level(6.0), LEVELS.len() - 1);
+        assert_eq!(heatmap.level(1.0), 0);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::incidents::Cause;
+use crate::provider::Source;
+use chrono::{DateTime, Local};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use tokio::time::Duration;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sample {
+    pub time: DateTime<Local>,
+    pub server: String,
+    pub online: bool,
+    pub players_online: u32,
+    pub players_max: u32,
+    #[serde(default)]
+    pub players: Vec<String>,
+    pub latency_ms: Option<f64>,
+    pub source: String,
+    // オフラインだった場合の原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<Cause>,
+}
+
+impl Sample {
+    pub fn from_status(server: &str, st: &crate::HavenStatus, source: Source, latency: Option<Duration>) -> Self {
+        Self {
+            time: Local::now(),
+            server: server.to_string(),
+            online: st.online,
+            players_online: st.players.online,
+            players_max: st.players.max,
+            players: st.players.list.clone().unwrap_or_default(),
+            latency_ms: latency.map(|d: Duration| d.as_secs_f64() * 1000.0),
+            source: source.key().to_string(),
+            cause: (!st.online).then(|| Cause::reported_by(source)),
+        }
+    }
+
+    pub fn offline(server: &str, source: &str, cause: Cause) -> Self {
+        Self {
+            time: Local::now(),
+            server: server.to_string(),
+            online: false,
+            players_online: 0,
+            players_max: 0,
+            players: Vec::new(),
+            latency_ms: None,
+            source: source.to_string(),
+            cause: Some(cause),
+        }
+    }
+
+    // すべての取得元が失敗したときは、最初に試した取得元とそのエラーを記録する
+    pub fn failed(server: &str, err: &(dyn std::error::Error + 'static)) -> Self {
+        match err.downcast_ref::<crate::provider::Failure>() {
+            Some(failure) => {
+                let (source, primary) = failure.primary();
+                Self::offline(server, source.key(), crate::incidents::classify(primary))
+            }
+            None => Self::offline(server, "none", crate::incidents::classify(err)),
+        }
+    }
+
+    // オフライン時や、名前が人数分そろっていない場合は一覧不明とする
+    // (Server List Ping や mcstatus.io の一覧は、最大12人ほどを無作為に選んだ sample にすぎない)
+    pub fn known_players(&self) -> Option<&[String]> {
+        if !self.online || self.players.len() != self.players_online as usize {
+            return None;
+        }
+        Some(&self.players)
+    }
+}
+
+// テスト用のサンプル。2026年1月の指定した日時に、誰もいないままオンラインだったものから組み立てる
+#[cfg(test)]
+impl Sample {
+    pub fn at(day: u32, hour: u32, minute: u32) -> Self {
+        use chrono::TimeZone;
+        Self {
+            time: Local.with_ymd_and_hms(2026, 1, day, hour, minute, 0).unwrap(),
+            server: "test".into(),
+            online: true,
+            players_online: 0,
+            players_max: 20,
+            players: Vec::new(),
+            latency_ms: None,
+            source: "slp".into(),
+            cause: None,
+        }
+    }
+
+    // 人数分そろったプレイヤー一覧
+    pub fn with_players(mut self, players: &[&str]) -> Self {
+        self.players = players.iter().map(|p: &&str| p.to_string()).collect();
+        self.players_online = players.len() as u32;
+        self
+    }
+
+    // 人数だけを変える。一覧より多ければ、一部の名前しか返ってこなかったサンプルになる
+    pub fn with_count(mut self, online: u32) -> Self {
+        self.players_online = online;
+        self
+    }
+
+    pub fn down(self, cause: Cause) -> Self {
+        Self { online: false, players_online: 0, players: Vec::new(), cause: Some(cause), ..self }
+    }
+}
+
+pub struct Store {
+    path: PathBuf,
+    server: String,
+}
+
+impl Store {
+    // 設定ファイルと同じディレクトリの history.jsonl に1行1レコードで追記する
+    pub fn open(server: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let path: PathBuf = confy::get_configuration_file_path("haole", "config")?.with_file_name("history.jsonl");
+        Ok(Self { path, server: server.to_string() })
+    }
+
+    #[cfg(test)]
+    fn at(path: PathBuf, server: &str) -> Self {
+        Self { path, server: server.to_string() }
+    }
+
+    pub fn append(&self, sample: &Sample) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file: File = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(sample)?)?;
+        Ok(())
+    }
+
+    pub fn load(&self, since: Option<DateTime<Local>>) -> Result<Vec<Sample>, Box<dyn std::error::Error>> {
+        let file: File = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut samples: Vec<Sample> = Vec::new();
+        for line in BufReader::new(file).lines() {
+            // 書き込み途中で終了した行などは読み飛ばす
+            let Ok(sample) = serde_json::from_str::<Sample>(&line?) else {
+                continue;
+            };
+            if sample.server == self.server && since.is_none_or(|t: DateTime<Local>| sample.time >= t) {
+                samples.push(sample);
+            }
+        }
+        Ok(samples)
+    }
+}
+
+pub fn format_time(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// stats・incidents・heatmap で、期間内に記録がなかったときの案内
+pub fn print_no_records(period: &str) {
+    println!("{} 直近 {} の記録がありません。TUI・--watch・exporter を実行すると記録されます。", "!!".yellow(), period);
+}
+
+pub fn format_duration(d: chrono::Duration) -> String {
+    let secs: i64 = d.num_seconds().max(0);
+    match (secs / 3600, secs / 60 % 60) {
+        (0, 0) => format!("{}秒", secs),
+        (0, m) => format!("{}分", m),
+        (h, m) => format!("{}時間{}分", h, m),
+    }
+}
+
+// 現在時刻から引いても桁あふれしないよう、期間の上限を100年にする
+const MAX_SINCE_DAYS: i64 = 36500;
+
+// "24h" や "7d" のような期間指定 (m: 分, h: 時間, d: 日, w: 週)
+#[derive(Clone, Debug)]
+pub struct Since {
+    pub label: String,
+    pub duration: chrono::Duration,
+}
+
+impl Since {
+    pub fn start(&self) -> DateTime<Local> {
+        Local::now() - self.duration
+    }
+}
+
+impl std::str::FromStr for Since {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("期間の指定が不正です: {} (例: 24h, 7d, 30d)", s);
+        let trimmed: &str = s.trim();
+        // 単位が全角文字などでも区切れるよう、最後の文字の位置で分ける
+        let (at, _) = trimmed.char_indices().last().ok_or_else(invalid)?;
+        let (num, unit) = trimmed.split_at(at);
+        let n: i64 = num.parse().ok().filter(|n: &i64| *n > 0).ok_or_else(invalid)?;
+        let duration: chrono::Duration = match unit {
+            "m" => chrono::Duration::try_minutes(n),
+            "h" => chrono::Duration::try_hours(n),
+            "d" => chrono::Duration::try_days(n),
+            "w" => chrono::Duration::try_weeks(n),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        if duration > chrono::Duration::days(MAX_SINCE_DAYS) {
+            return Err(format!("期間は {} 日以内で指定してください: {}", MAX_SINCE_DAYS, s));
+        }
+        Ok(Self { label: trimmed.to_string(), duration })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_since() {
+        for (input, minutes) in [("30m", 30), ("24h", 24 * 60), (" 7d ", 7 * 24 * 60), ("2w", 14 * 24 * 60)] {
+            let since: Since = input.parse().unwrap();
+            assert_eq!((since.label.as_str(), since.duration.num_minutes()), (input.trim(), minutes));
+        }
+        for input in ["", "d", "0d", "-1d", "1.5h", "10y", "1時", "１d", "100000000d"] {
+            assert!(input.parse::<Since>().is_err(), "{}", input);
+        }
+
+        let since: Since = format!("{}d", MAX_SINCE_DAYS).parse().unwrap();
+        assert!(since.start() < Local::now());
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path: PathBuf = std::env::temp_dir().join(format!("haole-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn store_round_trip_per_server() {
+        let path: PathBuf = temp_path("round-trip");
+        let store: Store = Store::at(path.clone(), "test");
+        assert!(store.load(None).unwrap().is_empty());
+
+        store.append(&Sample::at(1, 12, 0).with_players(&["Steve", "Alex"])).unwrap();
+        store.append(&Sample::at(1, 12, 5).down(Cause::Timeout)).unwrap();
+        Store::at(path.clone(), "other").append(&Sample { server: "other".into(), ..Sample::at(1, 12, 3) }).unwrap();
+
+        let samples: Vec<Sample> = store.load(None).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!((samples[0].time, samples[0].players.as_slice()), (Sample::at(1, 12, 0).time, ["Steve", "Alex"].map(String::from).as_slice()));
+        assert_eq!((samples[1].online, samples[1].cause), (false, Some(Cause::Timeout)));
+
+        let recent: Vec<Sample> = store.load(Some(Sample::at(1, 12, 1).time)).unwrap();
+        assert_eq!(recent.iter().map(|s: &Sample| s.time).collect::<Vec<_>>(), [Sample::at(1, 12, 5).time]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn store_skips_partial_lines() {
+        let path: PathBuf = temp_path("partial");
+        let store: Store = Store::at(path.clone(), "test");
+        store.append(&Sample::at(1, 12, 0)).unwrap();
+        // 書き込みの途中で終了した行
+        let line: String = serde_json::to_string(&Sample::at(1, 12, 1)).unwrap();
+        let mut file: File = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{}", &line[..line.len() / 2]).unwrap();
+        store.append(&Sample::at(1, 12, 2)).unwrap();
+
+        let samples: Vec<Sample> = store.load(None).unwrap();
+        assert_eq!(samples.iter().map(|s: &Sample| s.time).collect::<Vec<_>>(), [Sample::at(1, 12, 0).time, Sample::at(1, 12, 2).time]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::HaoleError;
+use base64::Engine;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+const DATA_URI_PREFIX: &str = "data:image/png;base64,";
+// アルファ値がこれ未満のピクセルは透明として扱う
+const ALPHA_THRESHOLD: u8 = 128;
+
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    pub png: Vec<u8>,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Icon {
+    pub fn from_data_uri(uri: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let encoded: &str = uri
+            .strip_prefix(DATA_URI_PREFIX)
+            .ok_or_else(|| HaoleError::parse("favicon が PNG の data URI ではありません。"))?;
+        // 古いサーバーは base64 の途中に改行を含めることがある
+        let encoded: String = encoded.chars().filter(|c: &char| !c.is_whitespace()).collect();
+        let png: Vec<u8> = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e: base64::DecodeError| HaoleError::parse(format!("favicon の base64 を解析できません: {}", e)))?;
+        Self::from_png(png)
+    }
+
+    pub fn from_png(png: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut decoder: png::Decoder<&[u8]> = png::Decoder::new(png.as_slice());
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader: png::Reader<&[u8]> = decoder
+            .read_info()
+            .map_err(|e: png::DecodingError| HaoleError::parse(format!("favicon の PNG を解析できません: {}", e)))?;
+        let mut buf: Vec<u8> = vec![0; reader.output_buffer_size()];
+        let frame: png::OutputInfo = reader
+            .next_frame(&mut buf)
+            .map_err(|e: png::DecodingError| HaoleError::parse(format!("favicon の PNG を解析できません: {}", e)))?;
+
+        let pixels: Vec<[u8; 4]> = match frame.color_type {
+            png::ColorType::Rgba => buf.chunks_exact(4).map(|p: &[u8]| [p[0], p[1], p[2], p[3]]).collect(),
+            png::ColorType::Rgb => buf.chunks_exact(3).map(|p: &[u8]| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).map(|p: &[u8]| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buf.iter().map(|v: &u8| [*v, *v, *v, 255]).collect(),
+            png::ColorType::Indexed => return Err(HaoleError::parse("favicon のパレット形式に対応していません。")),
+        };
+        Ok(Self {
+            width: frame.width,
+            height: frame.height,
+            png,
+            pixels: pixels.into_iter().take((frame.width * frame.height) as usize).collect(),
+        })
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        let [r, g, b, a] = *self.pixels.get((y * self.width + x) as usize)?;
+        (a >= ALPHA_THRESHOLD).then_some([r, g, b])
+    }
+
+    // 指定サイズに縮小したときの各ピクセルを、元画像の対応する範囲の平均色で求める
+    fn sample(&self, size: u32) -> Vec<Vec<Option<[u8; 3]>>> {
+        let size: u32 = size.clamp(1, self.width.min(self.height).max(1));
+        (0..size)
+            .map(|row: u32| {
+                (0..size)
+                    .map(|col: u32| {
+                        let (x0, x1) = (col * self.width / size, ((col + 1) * self.width / size).max(col * self.width / size + 1));
+                        let (y0, y1) = (row * self.height / size, ((row + 1) * self.height / size).max(row * self.height / size + 1));
+                        let opaque: Vec<[u8; 3]> = (y0..y1).flat_map(|y: u32| (x0..x1).filter_map(move |x: u32| self.pixel(x, y))).collect();
+                        let area: usize = ((x1 - x0) * (y1 - y0)) as usize;
+                        if opaque.len() * 2 < area {
+                            return None;
+                        }
+                        let sum: [u32; 3] = opaque.iter().fold([0; 3], |acc: [u32; 3], p: &[u8; 3]| {
+                            [acc[0] + p[0] as u32, acc[1] + p[1] as u32, acc[2] + p[2] as u32]
+                        });
+                        let n: u32 = opaque.len() as u32;
+                        Some([(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // 上半分を文字色、下半分を背景色にした「▀」で縦2ピクセルを1文字に収める
+    pub fn to_ansi(&self, size: u32) -> String {
+        let grid: Vec<Vec<Option<[u8; 3]>>> = self.sample(size);
+        let mut out: String = String::new();
+        for pair in grid.chunks(2) {
+            for (col, top) in pair[0].iter().enumerate() {
+                let bottom: Option<[u8; 3]> = pair.get(1).and_then(|row: &Vec<Option<[u8; 3]>>| row[col]);
+                match (top, bottom) {
+                    (Some(t), Some(b)) => out.push_str(&format!("\x1b[38;2;{};{};{};48;2;{};{};{}m▀", t[0], t[1], t[2], b[0], b[1], b[2])),
+                    (Some(t), None) => out.push_str(&format!("\x1b[0;38;2;{};{};{}m▀", t[0], t[1], t[2])),
+                    (None, Some(b)) => out.push_str(&format!("\x1b[0;38;2;{};{};{}m▄", b[0], b[1], b[2])),
+                    (None, None) => out.push_str("\x1b[0m "),
+                }
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    pub fn to_lines(&self, size: u32) -> Vec<Line<'static>> {
+        let rgb = |p: [u8; 3]| Color::Rgb(p[0], p[1], p[2]);
+        let grid: Vec<Vec<Option<[u8; 3]>>> = self.sample(size);
+        grid.chunks(2)
+            .map(|pair: &[Vec<Option<[u8; 3]>>]| {
+                let spans: Vec<Span<'static>> = pair[0]
+                    .iter()
+                    .enumerate()
+                    .map(|(col, top)| {
+                        let bottom: Option<[u8; 3]> = pair.get(1).and_then(|row: &Vec<Option<[u8; 3]>>| row[col]);
+                        match (top, bottom) {
+                            (Some(t), Some(b)) => Span::styled("▀", Style::default().fg(rgb(*t)).bg(rgb(b))),
+                            (Some(t), None) => Span::styled("▀", Style::default().fg(rgb(*t))),
+                            (None, Some(b)) => Span::styled("▄", Style::default().fg(rgb(b))),
+                            (None, None) => Span::raw(" "),
+                        }
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+        let mut png: Vec<u8> = Vec::new();
+        let mut encoder: png::Encoder<&mut Vec<u8>> = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(rgba).unwrap();
+        png
+    }
+
+    #[test]
+    fn decodes_data_uri_and_renders_half_blocks() {
+        // 上段: 赤と透明、下段: 青と緑
+        let png: Vec<u8> = encode(2, 2, &[255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 255, 255, 0, 255, 0, 255]);
+        let uri: String = format!("{}{}", DATA_URI_PREFIX, base64::engine::general_purpose::STANDARD.encode(&png));
+        let icon: Icon = Icon::from_data_uri(&uri).unwrap();
+        assert_eq!((icon.width, icon.height), (2, 2));
+        assert_eq!(icon.png, png);
+        assert_eq!(
+            icon.to_ansi(2),
+            "\x1b[38;2;255;0;0;48;2;0;0;255m▀\x1b[0;38;2;0;255;0m▄\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn rejects_non_png_uri() {
+        assert!(Icon::from_data_uri("data:image/jpeg;base64,AAAA").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::config::ServerProfile;
+use crate::error::HaoleError;
+use crate::history::{self, Sample, Since, Store};
+use crate::output::{self, OutputFormat};
+use crate::provider::Source;
+use chrono::{DateTime, Local};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+
+const SLA_TARGETS: [f64; 3] = [99.0, 99.9, 99.99];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Cause {
+    ApiOffline,
+    McstatusOffline,
+    Timeout,
+    Dns,
+    BadJson,
+    Unreachable,
+    Unknown,
+}
+
+impl Cause {
+    pub fn label(self) -> &'static str {
+        match self {
+            Cause::ApiOffline => "API がオフラインと応答",
+            Cause::McstatusOffline => "mcstatus.io がオフラインと応答",
+            Cause::Timeout => "タイムアウト",
+            Cause::Dns => "DNS の名前解決に失敗",
+            Cause::BadJson => "不正な JSON",
+            Cause::Unreachable => "接続できない",
+            Cause::Unknown => "不明",
+        }
+    }
+
+    // 取得元がオフラインと答えたときの原因
+    pub fn reported_by(source: Source) -> Self {
+        match source {
+            Source::HavenApi => Cause::ApiOffline,
+            Source::Mcstatus => Cause::McstatusOffline,
+            // 直接問い合わせは応答があればオンラインなので、ここには来ない
+            Source::Slp => Cause::Unknown,
+        }
+    }
+}
+
+// 取得に失敗したときのエラーを、原因の種類に分ける
+pub fn classify(err: &(dyn Error + 'static)) -> Cause {
+    let mut current: Option<&(dyn Error + 'static)> = Some(err);
+    while let Some(e) = current {
+        if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return Cause::Timeout;
+            }
+            if e.is_decode() {
+                return Cause::BadJson;
+            }
+        }
+        if e.is::<serde_json::Error>() || matches!(e.downcast_ref::<HaoleError>(), Some(HaoleError::Parse(_))) {
+            return Cause::BadJson;
+        }
+        if e.downcast_ref::<std::io::Error>().is_some_and(|io: &std::io::Error| io.kind() == std::io::ErrorKind::TimedOut) {
+            return Cause::Timeout;
+        }
+        if crate::error::is_dns_failure(e) {
+            return Cause::Dns;
+        }
+        current = e.source();
+    }
+    Cause::Unreachable
+}
+
+pub struct Incident {
+    pub start: DateTime<Local>,
+    // まだ復旧していなければ None
+    pub end: Option<DateTime<Local>>,
+    pub cause: Cause,
+}
+
+impl Incident {
+    pub fn duration(&self, now: DateTime<Local>) -> chrono::Duration {
+        self.end.unwrap_or(now) - self.start
+    }
+}
+
+// オフラインになってから、次にオンラインを確認するまでを1件の障害とする
+pub fn from_history(samples: &[Sample]) -> Vec<Incident> {
+    let mut incidents: Vec<Incident> = Vec::new();
+    for sample in samples {
+        let open: bool = incidents.last().is_some_and(|i: &Incident| i.end.is_none());
+        match (sample.online, open) {
+            (false, false) => incidents.push(Incident {
+                start: sample.time,
+                end: None,
+                cause: sample.cause.unwrap_or(Cause::Unknown),
+            }),
+            (true, true) => {
+                if let Some(last) = incidents.last_mut() {
+                    last.end = Some(sample.time);
+                }
+            }
+            _ => {}
+        }
+    }
+    incidents
+}
+
+// 期間のうち停止していなかった時間の割合
+pub fn uptime_percent(incidents: &[Incident], from: DateTime<Local>, now: DateTime<Local>) -> f64 {
+    let total: i64 = (now - from).num_seconds();
+    if total <= 0 {
+        return 100.0;
+    }
+    let down: i64 = incidents
+        .iter()
+        .map(|i: &Incident| (i.end.unwrap_or(now).min(now) - i.start.max(from)).num_seconds().max(0))
+        .sum();
+    100.0 * (total - down.min(total)) as f64 / total as f64
+}
+
+pub fn run(target: &ServerProfile, since: &Since, format: OutputFormat) -> Result<bool, Box<dyn std::error::Error>> {
+    let store: Store = Store::open(&target.name)?;
+    let samples: Vec<Sample> = store.load(Some(since.start()))?;
+    let incidents: Vec<Incident> = from_history(&samples);
+    let now: DateTime<Local> = Local::now();
+    // 記録を始める前の時間は稼働率に含めない
+    let from: DateTime<Local> = samples.first().map_or(now, |s: &Sample| s.time);
+    let uptime: f64 = uptime_percent(&incidents, from, now);
+
+    if format.is_structured() {
+        output::emit(format, "incidents", &target.name, json!({
+            "since": since.label,
+            "samples": samples.len(),
+            "uptime_percent": uptime,
+            "sla": SLA_TARGETS.iter().map(|t: &f64| json!({ "target": t, "met": uptime >= *t })).collect::<Vec<_>>(),
+            "incidents": incidents.iter().map(|i: &Incident| json!({
+                "start": i.start.to_rfc3339(),
+                "end": i.end.map(|t: DateTime<Local>| t.to_rfc3339()),
+                "duration_secs": i.duration(now).num_seconds(),
+                "cause": i.cause,
+            })).collect::<Vec<_>>(),
+        }))?;
+        return Ok(!samples.is_empty());
+    }
+
+    if samples.is_empty() {
+        history::print_no_records(&since.label);
+        return Ok(false);
+    }
+
+    println!("{} 直近 {} の障害 ({} 件)\n", ">>".blue(), since.label, incidents.len());
+    if incidents.is_empty() {
+        println!("障害は記録されていません。");
+    }
+    for i in &incidents {
+        let end: ColoredString = match i.end {
+            Some(t) => history::format_time(t).normal(),
+            None => "継続中".red().bold(),
+        };
+        println!("{} 〜 {}  {} ({})", history::format_time(i.start), end, i.cause.label().yellow(), history::format_duration(i.duration(now)));
+    }
+
+    println!("\n稼働率: {:.3}% (観測期間 {})", uptime, history::format_duration(now - from));
+    let sla: Vec<String> = SLA_TARGETS
+        .iter()
+        .map(|t: &f64| {
+            let result: ColoredString = if uptime >= *t { "達成".green() } else { "未達".red() };
+            format!("{}%: {}", t, result)
+        })
+        .collect();
+    println!("SLA {}", sla.join(" / "));
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike};
+
+    #[test]
+    fn builds_incidents_and_uptime() {
+        let samples: Vec<Sample> = vec![
+            Sample::at(1, 12, 0),
+            Sample::at(1, 12, 10).down(Cause::Timeout),
+            Sample::at(1, 12, 15).down(Cause::Dns),
+            Sample::at(1, 12, 20),
+            Sample::at(1, 12, 50).down(Cause::ApiOffline),
+        ];
+        let incidents: Vec<Incident> = from_history(&samples);
+        let summary: Vec<(Cause, Option<u32>)> = incidents.iter().map(|i: &Incident| (i.cause, i.end.map(|t: DateTime<Local>| t.minute()))).collect();
+        assert_eq!(summary, [(Cause::Timeout, Some(20)), (Cause::ApiOffline, None)]);
+
+        // 60分のうち 10分 + 10分 停止
+        let now: DateTime<Local> = Local.with_ymd_and_hms(2026, 1, 1, 13, 0, 0).unwrap();
+        let uptime: f64 = uptime_percent(&incidents, samples[0].time, now);
+        assert!((uptime - 100.0 * 40.0 / 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn blames_the_source_that_reported_offline() {
+        let st: crate::HavenStatus = crate::HavenStatus {
+            online: false,
+            players: crate::Players { online: 0, max: 0, list: None },
+            version: String::new(),
+        };
+        assert_eq!(Sample::from_status("test", &st, Source::HavenApi, None).cause, Some(Cause::ApiOffline));
+        let sample: Sample = Sample::from_status("test", &st, Source::Mcstatus, None);
+        assert_eq!((sample.source.as_str(), sample.cause), ("mcstatus", Some(Cause::McstatusOffline)));
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let timeout: Box<dyn Error> = Box::new(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"));
+        assert_eq!(classify(timeout.as_ref()), Cause::Timeout);
+        let dns: Box<dyn Error> = Box::new(std::io::Error::other("failed to lookup address information: Name or service not known"));
+        assert_eq!(classify(dns.as_ref()), Cause::Dns);
+        let json: Box<dyn Error> = Box::new(serde_json::from_str::<u32>("{").unwrap_err());
+        assert_eq!(classify(json.as_ref()), Cause::BadJson);
+        let refused: Box<dyn Error> = Box::new(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        assert_eq!(classify(refused.as_ref()), Cause::Unreachable);
+    }
+}
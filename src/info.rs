@@ -0,0 +1,151 @@
+use crate::config::ServerProfile;
+use crate::output::{self, OutputFormat};
+use crate::provider::Source;
+use crate::{motd, HavenStatus, McStatusIOResponse};
+use colored::*;
+use serde::Serialize;
+
+// 値と、その値をどの取得元から得たか
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Field<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Field<T> {
+    fn new(value: T, source: Source) -> Self {
+        Self { value, source }
+    }
+}
+
+// HavenMC API と mcstatus.io の結果をまとめた、サーバーの状態
+#[derive(Serialize, Default)]
+pub struct ServerInfo {
+    pub online: Option<Field<bool>>,
+    pub players_online: Option<Field<u32>>,
+    pub players_max: Option<Field<u32>>,
+    pub players: Option<Field<Vec<String>>>,
+    pub version: Option<Field<String>>,
+    pub protocol: Option<Field<u32>>,
+    pub host: Option<Field<String>>,
+    pub ip_address: Option<Field<String>>,
+    pub port: Option<Field<u16>>,
+    pub motd: Option<Field<String>>,
+}
+
+// mcstatus.io (失敗時は直接問い合わせ) の結果を土台に、HavenMC API が答えた項目はそちらで上書きする
+pub fn merge(haven: Option<HavenStatus>, details: Option<(Source, McStatusIOResponse)>) -> ServerInfo {
+    let mut info: ServerInfo = ServerInfo::default();
+    if let Some((source, resp)) = details {
+        let st: HavenStatus = HavenStatus::from_mcstatusio(&resp);
+        info.online = Some(Field::new(st.online, source));
+        // オフラインのときは人数やバージョンが返ってこない
+        if st.online {
+            info.players_online = Some(Field::new(st.players.online, source));
+            info.players_max = Some(Field::new(st.players.max, source));
+            info.players = st.players.list.map(|list: Vec<String>| Field::new(list, source));
+            info.version = Some(Field::new(st.version, source));
+            info.protocol = Some(Field::new(resp.version.protocol, source));
+            info.motd = Some(Field::new(motd::to_clean(&motd::parse_legacy(&resp.motd.raw)), source));
+        }
+        info.host = Some(Field::new(resp.host, source));
+        info.ip_address = Some(Field::new(resp.ip_address, source));
+        info.port = Some(Field::new(resp.port, source));
+    }
+    if let Some(st) = haven {
+        info.online = Some(Field::new(st.online, Source::HavenApi));
+        info.players_online = Some(Field::new(st.players.online, Source::HavenApi));
+        info.players_max = Some(Field::new(st.players.max, Source::HavenApi));
+        if let Some(list) = st.players.list {
+            info.players = Some(Field::new(list, Source::HavenApi));
+        }
+        info.version = Some(Field::new(st.version, Source::HavenApi));
+    }
+    info
+}
+
+fn print_field<T>(label: &str, field: &Option<Field<T>>, show: impl Fn(&T) -> String) {
+    match field {
+        Some(f) => println!("{}: {} {}", label, show(&f.value).magenta(), format!("({})", f.source.label()).dimmed()),
+        None => println!("{}: {}", label, "取得できませんでした".dimmed()),
+    }
+}
+
+pub async fn run(target: &ServerProfile, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let (haven, mcstatus) = tokio::join!(
+        crate::fetch_haven_status(target),
+        crate::fetch_haven_status_by_mcstatusio(target),
+    );
+    let details: Result<(Source, McStatusIOResponse), Box<dyn std::error::Error>> = match mcstatus {
+        Ok(resp) => Ok((Source::Mcstatus, resp)),
+        Err(_) => crate::fetch_haven_status_by_slp(target).await.map(|resp: McStatusIOResponse| (Source::Slp, resp)),
+    };
+    let haven: Option<HavenStatus> = haven.ok();
+    // どちらからも取得できなければエラーにする
+    let details: Option<(Source, McStatusIOResponse)> = match (details, &haven) {
+        (Ok(details), _) => Some(details),
+        (Err(_), Some(_)) => None,
+        (Err(e), None) => return Err(e),
+    };
+    let info: ServerInfo = merge(haven, details);
+
+    if format.is_structured() {
+        return output::emit(format, "info", &target.name, serde_json::to_value(&info)?);
+    }
+
+    println!("{} {} のサーバー情報\n", ">>".blue(), target.name);
+    print_field("オンライン", &info.online, |v: &bool| if *v { "はい".into() } else { "いいえ".into() });
+    print_field("プレイヤー数", &info.players_online, |v: &u32| v.to_string());
+    print_field("最大人数", &info.players_max, |v: &u32| v.to_string());
+    print_field("プレイヤー", &info.players, |v: &Vec<String>| if v.is_empty() { "なし".into() } else { v.join(", ") });
+    print_field("バージョン", &info.version, |v: &String| v.clone());
+    print_field("プロトコル", &info.protocol, |v: &u32| v.to_string());
+    print_field("ホスト", &info.host, |v: &String| v.clone());
+    print_field("IPアドレス", &info.ip_address, |v: &String| v.clone());
+    print_field("ポート", &info.port, |v: &u16| v.to_string());
+    print_field("MOTD", &info.motd, |v: &String| v.replace('\n', " / "));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{McStatusIOResponseMotd, McStatusIOResponsePlayers, McStatusIOResponseVersion, Players};
+
+    fn details(online: bool) -> McStatusIOResponse {
+        McStatusIOResponse {
+            online,
+            host: "play.example.com".into(),
+            ip_address: "192.0.2.1".into(),
+            port: 25565,
+            version: McStatusIOResponseVersion { name_clean: "1.21.4".into(), protocol: 769 },
+            // 名前の一覧は非公開
+            players: Some(McStatusIOResponsePlayers { online: 3, max: 20, list: Vec::new() }),
+            motd: McStatusIOResponseMotd { raw: "§aWelcome".into() },
+            icon: None,
+        }
+    }
+
+    #[test]
+    fn prefers_haven_api_and_fills_in_the_rest() {
+        let haven: HavenStatus = HavenStatus {
+            online: true,
+            players: Players { online: 4, max: 20, list: Some(vec!["Steve".into()]) },
+            version: "Paper 1.21.4".into(),
+        };
+        let info: ServerInfo = merge(Some(haven), Some((Source::Mcstatus, details(true))));
+        assert_eq!(info.players_online, Some(Field::new(4, Source::HavenApi)));
+        assert_eq!(info.players, Some(Field::new(vec!["Steve".to_string()], Source::HavenApi)));
+        assert_eq!(info.protocol, Some(Field::new(769, Source::Mcstatus)));
+        assert_eq!(info.motd, Some(Field::new("Welcome".to_string(), Source::Mcstatus)));
+
+        let info: ServerInfo = merge(None, Some((Source::Slp, details(true))));
+        assert_eq!(info.version, Some(Field::new("1.21.4".to_string(), Source::Slp)));
+        assert_eq!(info.players, None);
+
+        let info: ServerInfo = merge(None, Some((Source::Mcstatus, details(false))));
+        assert_eq!(info.online, Some(Field::new(false, Source::Mcstatus)));
+        assert_eq!((info.players_online, info.version), (None, None));
+        assert_eq!(info.port, Some(Field::new(25565, Source::Mcstatus)));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::HaoleError;
+use crate::slp::{SlpPlayers, SlpResponse, SlpStatus, SlpVersion};
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+// 1.6 の MC|PingHost で名乗るプロトコルバージョン (1.6.4)
+const PING_HOST_PROTOCOL: u8 = 78;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LegacyFormat {
+    // 1.4 ~ 1.6: 0xFE 0x01 + MC|PingHost
+    V1_6,
+    // Beta 1.8 ~ 1.3: 0xFE のみ
+    Beta,
+}
+
+fn push_utf16(buf: &mut Vec<u8>, s: &str) {
+    for unit in s.encode_utf16() {
+        buf.extend_from_slice(&unit.to_be_bytes());
+    }
+}
+
+fn request(format: LegacyFormat, host: &str, port: u16) -> Vec<u8> {
+    match format {
+        LegacyFormat::Beta => vec![0xFE],
+        LegacyFormat::V1_6 => {
+            let host_len: u16 = host.encode_utf16().count() as u16;
+            let mut buf: Vec<u8> = vec![0xFE, 0x01, 0xFA];
+            buf.extend_from_slice(&11u16.to_be_bytes());
+            push_utf16(&mut buf, "MC|PingHost");
+            buf.extend_from_slice(&(7 + 2 * host_len).to_be_bytes());
+            buf.push(PING_HOST_PROTOCOL);
+            buf.extend_from_slice(&host_len.to_be_bytes());
+            push_utf16(&mut buf, host);
+            buf.extend_from_slice(&(port as i32).to_be_bytes());
+            buf
+        }
+    }
+}
+
+pub fn parse_response(text: &str) -> Result<SlpStatus, Box<dyn std::error::Error>> {
+    let (protocol, name, motd, online, max) = if let Some(rest) = text.strip_prefix("§1\0") {
+        let fields: Vec<&str> = rest.split('\0').collect();
+        if fields.len() != 5 {
+            return Err(HaoleError::parse(format!("レガシーPingの応答のフィールド数が不正です: {}", fields.len())));
+        }
+        (fields[0].parse::<i32>()?, fields[1].to_string(), fields[2], fields[3], fields[4])
+    } else {
+        let fields: Vec<&str> = text.rsplitn(3, '§').collect();
+        if fields.len() != 3 {
+            return Err(HaoleError::parse("レガシーPingの応答を解析できませんでした。"));
+        }
+        (0, "<1.4".to_string(), fields[2], fields[1], fields[0])
+    };
+
+    Ok(SlpStatus {
+        version: SlpVersion { name, protocol },
+        players: Some(SlpPlayers {
+            online: online.parse()?,
+            max: max.parse()?,
+            sample: None,
+        }),
+        description: serde_json::Value::String(motd.to_string()),
+        favicon: None,
+    })
+}
+
+pub async fn status(format: LegacyFormat, host: &str, port: u16) -> Result<SlpResponse, Box<dyn std::error::Error>> {
+    timeout(IO_TIMEOUT, status_inner(format, host, port))
+        .await
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("{}:{} へのレガシーPingがタイムアウトしました。", host, port),
+            )
+        })?
+}
+
+async fn status_inner(format: LegacyFormat, host: &str, port: u16) -> Result<SlpResponse, Box<dyn std::error::Error>> {
+    let mut stream: TcpStream = TcpStream::connect((host, port)).await?;
+    let address: SocketAddr = stream.peer_addr()?;
+
+    let sent: Instant = Instant::now();
+    stream.write_all(&request(format, host, port)).await?;
+
+    let packet_id: u8 = stream.read_u8().await?;
+    let latency: Duration = sent.elapsed();
+    if packet_id != 0xFF {
+        return Err(HaoleError::parse(format!("Kick パケットではない応答を受信しました: {:#04x}", packet_id)));
+    }
+    let len: usize = stream.read_u16().await? as usize;
+    let mut body: Vec<u8> = vec![0; len * 2];
+    stream.read_exact(&mut body).await?;
+    let units: Vec<u16> = body
+        .chunks_exact(2)
+        .map(|pair: &[u8]| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    let text: String = String::from_utf16(&units)?;
+
+    Ok(SlpResponse {
+        status: parse_response(&text)?,
+        latency,
+        address,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_response_formats() {
+        let st: SlpStatus = parse_response(&["§1", "127", "1.6.4", "§6HavenMC", "3", "20"].join("\0")).unwrap();
+        assert_eq!((st.version.protocol, st.version.name.as_str()), (127, "1.6.4"));
+        assert_eq!(st.description, "§6HavenMC");
+        let players: SlpPlayers = st.players.unwrap();
+        assert_eq!((players.online, players.max), (3, 20));
+
+        let st: SlpStatus = parse_response("A §cBeta§r server§5§10").unwrap();
+        assert_eq!(st.description, "A §cBeta§r server");
+        let players: SlpPlayers = st.players.unwrap();
+        assert_eq!((players.online, players.max), (5, 10));
+
+        assert!(parse_response("garbage").is_err());
+    }
+
+    #[test]
+    fn ping_host_request_layout() {
+        let buf: Vec<u8> = request(LegacyFormat::V1_6, "ab", 25565);
+        assert_eq!(&buf[..5], &[0xFE, 0x01, 0xFA, 0x00, 0x0B]);
+        let tail: &[u8] = &buf[5 + 22..];
+        assert_eq!(tail, &[0x00, 0x0B, PING_HOST_PROTOCOL, 0x00, 0x02, 0x00, b'a', 0x00, b'b', 0x00, 0x00, 0x63, 0xDD]);
+        assert_eq!(request(LegacyFormat::Beta, "ab", 25565), [0xFE]);
+    }
+}
@@ -1,39 +1,41 @@
-use clap::{Parser, Subcommand};
+mod bedrock;
+mod config;
+mod error;
+mod events;
+mod exporter;
+mod friends;
+mod heatmap;
+mod history;
+mod icon;
+mod incidents;
+mod info;
+mod legacy;
+mod motd;
+mod output;
+mod playtime;
+mod provider;
+mod query;
+mod slp;
+mod stats;
+mod tui;
+
+use clap::{CommandFactory, Parser, Subcommand};
 use colored::*;
 use std::collections::VecDeque;
-use std::process::Command;
 use serde::Deserialize;
 use tokio::time::{Duration};
 use std::io::{stdout};
-use chrono::Local;
+use std::process::ExitCode;
 use crossterm::{
     execute,
     terminal::{Clear, ClearType},
     cursor::MoveTo,
     event::{self, Event, KeyCode, KeyEventKind},
 };
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Layout, Constraint, Direction},
-    widgets::{Block, Borders, Paragraph, List, ListItem},
-    Terminal,
-};
-
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
-struct HaoleConfig {
-    mode: String,
-}
-
-impl Default for HaoleConfig {
-    fn default() -> Self {
-        Self { mode: "cli".into() }
-    }
-}
-
-struct HistoryEntry {
-    time: String,
-    online: u32,
-}
+use config::{Edition, HaoleConfig, ServerProfile};
+use error::{HaoleError, EXIT_CONFIG, EXIT_FALSE, EXIT_OK};
+use output::OutputFormat;
+use serde_json::json;
 
 #[derive(Deserialize)]
 struct HavenStatus {
@@ -44,40 +46,131 @@ struct HavenStatus {
 
 #[derive(Deserialize)]
 struct McStatusIOResponse {
+    #[serde(default)]
+    online: bool,
     host: String,
     ip_address: String,
     port: u16,
+    #[serde(default)]
     version: McStatusIOResponseVersion,
+    #[serde(default)]
+    players: Option<McStatusIOResponsePlayers>,
+    #[serde(default)]
     motd: McStatusIOResponseMotd,
+    #[serde(default)]
+    icon: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 struct McStatusIOResponseVersion {
+    #[serde(default)]
+    name_clean: String,
     protocol: u32,
 }
 
 #[derive(Deserialize)]
+struct McStatusIOResponsePlayers {
+    online: u32,
+    max: u32,
+    #[serde(default)]
+    list: Vec<McStatusIOResponsePlayer>,
+}
+
+#[derive(Deserialize)]
+struct McStatusIOResponsePlayer {
+    name_clean: String,
+}
+
+#[derive(Deserialize, Default)]
 struct McStatusIOResponseMotd {
     raw: String,
-    clean: String,
-    html: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, serde::Serialize)]
 struct Players {
     online: u32,
     max: u32,
     list: Option<Vec<String>>,
 }
 
+impl HavenStatus {
+    fn from_slp(resp: &slp::SlpResponse) -> Self {
+        let players: Players = match &resp.status.players {
+            Some(p) => Players {
+                online: p.online,
+                max: p.max,
+                list: p.sample.as_ref().map(|sample: &Vec<slp::SlpSample>| sample.iter().map(|s: &slp::SlpSample| s.name.clone()).collect()),
+            },
+            None => Players { online: 0, max: 0, list: None },
+        };
+        Self {
+            online: true,
+            players,
+            version: resp.status.version.name.clone(),
+        }
+    }
+
+    fn from_mcstatusio(resp: &McStatusIOResponse) -> Self {
+        let players: Players = match &resp.players {
+            Some(p) => Players {
+                online: p.online,
+                max: p.max,
+                // 人数がいるのに一覧が空の場合は、名前が非公開とみなす
+                list: (p.online == 0 || !p.list.is_empty()).then(|| p.list.iter().map(|pl: &McStatusIOResponsePlayer| pl.name_clean.clone()).collect()),
+            },
+            None => Players { online: 0, max: 0, list: None },
+        };
+        Self {
+            online: resp.online,
+            players,
+            version: resp.version.name_clean.clone(),
+        }
+    }
+}
+
+impl McStatusIOResponse {
+    fn from_slp(host: &str, resp: &slp::SlpResponse) -> Self {
+        let raw: String = motd::to_legacy(&resp.status.description);
+        Self {
+            online: true,
+            host: host.to_string(),
+            ip_address: resp.address.ip().to_string(),
+            port: resp.address.port(),
+            version: McStatusIOResponseVersion {
+                name_clean: resp.status.version.name.clone(),
+                protocol: resp.status.version.protocol.max(0) as u32,
+            },
+            players: resp.status.players.as_ref().map(|p: &slp::SlpPlayers| McStatusIOResponsePlayers {
+                online: p.online,
+                max: p.max,
+                list: p.sample.iter().flatten().map(|s: &slp::SlpSample| McStatusIOResponsePlayer { name_clean: s.name.clone() }).collect(),
+            }),
+            motd: McStatusIOResponseMotd { raw },
+            icon: resp.status.favicon.clone(),
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "haole", about = "HavenMC Status CLI/TUI Tool", version = env!("CARGO_PKG_VERSION"))]
 struct Cli {
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 
     #[arg(short, long, global = true)]
     watch: Option<Option<u64>>,
+
+    #[arg(long, global = true, help = "設定ファイルに登録したサーバープロファイル名")]
+    server: Option<String>,
+
+    #[arg(long, global = true, help = "接続先のアドレス (host または host:port)")]
+    host: Option<String>,
+
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Text, help = "出力形式")]
+    output: OutputFormat,
+
+    #[arg(short, long, global = true, help = "何も出力せず終了コードだけで結果を返します")]
+    quiet: bool,
 }
 
 #[derive(Subcommand)]
@@ -87,17 +180,28 @@ enum Commands {
     #[command(alias = "pl", about = "現在オンラインのプレイヤー名を表示します。")]
     Players,
     #[command(about = "現在のプレイヤー数を表示します。")]
-    Pq,
+    Pq {
+        #[arg(long, value_enum)]
+        edition: Option<Edition>,
+    },
     #[command(about = "現在のプレイヤー数とオンラインのプレイヤー名を表示します。")]
     Pall,
+    #[command(about = "HavenMC API と mcstatus.io の情報をまとめて、取得元とともに表示します。")]
+    Info,
     #[command(alias = "isonline", about = "サーバーがオンラインかどうかを確認します。")]
-    IsOnline,
+    IsOnline {
+        #[arg(long, value_enum)]
+        edition: Option<Edition>,
+    },
     #[command(alias = "isoffline", about = "サーバーがオフラインかどうかを確認します。")]
     IsOffline,
     #[command(about = "Haoleのバージョンを表示します。")]
     Version,
     #[command(alias = "sver", about = "サーバーのバージョンを取得します。")]
-    ServerVersion,
+    ServerVersion {
+        #[arg(long, value_enum)]
+        edition: Option<Edition>,
+    },
     #[command(about = "サーバーのIPアドレスを取得します。")]
     Ip,
     #[command(about = "サーバーのホスト名を取得します。")]
@@ -108,10 +212,12 @@ enum Commands {
     Port,
     #[command(about = "サーバーのMOTDを取得します。")]
     Motd {
-        #[arg(short, long)]
-        raw: Option<String>,
-        clean: Option<String>,
-        html: Option<String>,
+        #[arg(long, value_enum, help = "出力形式 (省略時は clean)")]
+        format: Option<motd::MotdFormat>,
+        #[arg(long, conflicts_with = "format", help = "Minecraft クライアントと同じ色・書式でMOTDを表示します (--format ansi と同じ)")]
+        render: bool,
+        #[arg(long, value_enum)]
+        edition: Option<Edition>,
     },
     #[command(about = "Haoleの動作モードを設定または表示します。")]
     Mode {
@@ -121,45 +227,275 @@ enum Commands {
     Update,
     #[command(about = "サーバーにPingを送信します。")]
     Ping,
+    #[command(about = "Queryプロトコルでプレイヤー一覧やプラグイン情報を取得します。")]
+    Query {
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    #[command(about = "設定ファイルに登録されたサーバープロファイルを表示します。")]
+    Servers,
+    #[command(about = "Prometheus 形式のメトリクスを /metrics で公開します。")]
+    Exporter {
+        #[arg(long, default_value = "127.0.0.1:9150")]
+        listen: std::net::SocketAddr,
+        #[arg(long, default_value_t = 15, help = "ステータスを取得する間隔 (秒)")]
+        interval: u64,
+    },
+    #[command(about = "プレイヤーの参加・退出イベントを表示します。")]
+    Events {
+        #[arg(long, default_value = "24h", help = "表示する期間 (例: 1h, 24h, 7d)")]
+        since: history::Since,
+        #[arg(short, long, help = "新しいイベントを継続して表示します")]
+        follow: bool,
+        #[arg(long, default_value_t = 5, help = "--follow 時にステータスを取得する間隔 (秒)")]
+        interval: u64,
+    },
+    #[command(about = "記録された履歴からプレイヤー数や稼働率の統計を表示します。")]
+    Stats {
+        #[arg(long, default_value = "24h", help = "集計する期間 (例: 24h, 7d, 30d)")]
+        since: history::Since,
+    },
+    #[command(about = "記録された履歴から曜日・時間帯別の平均プレイヤー数を表示します。")]
+    Heatmap {
+        #[arg(long, default_value_t = heatmap::DEFAULT_WEEKS, value_parser = clap::value_parser!(u32).range(1..=heatmap::MAX_WEEKS as i64), help = "集計する週数 (最大 520)")]
+        weeks: u32,
+    },
+    #[command(about = "記録された履歴から障害の一覧と稼働率を表示します。")]
+    Incidents {
+        #[arg(long, default_value = "30d", help = "表示する期間 (例: 24h, 7d, 30d)")]
+        since: history::Since,
+    },
+    #[command(about = "サーバーアイコン (favicon) を保存または表示します。")]
+    Icon {
+        #[arg(long, value_name = "PATH", help = "PNG ファイルとして保存します")]
+        save: Option<std::path::PathBuf>,
+        #[arg(long, help = "端末にアイコンを表示します (--save を指定しない場合の既定)")]
+        show: bool,
+    },
+    #[command(about = "モードに関係なくTUIを起動します。")]
+    Tui,
+    #[command(about = "フレンドリストを管理し、フレンドのログインを通知します。")]
+    Friends {
+        #[command(subcommand)]
+        action: FriendsAction,
+    },
 }
 
-async fn fetch_haven_status() -> Result<HavenStatus, Box<dyn std::error::Error>> {
-    let url: &str = "https://api.havenmc.jp/status";
-    let resp: HavenStatus = reqwest::get(url).await?.json().await?;
+#[derive(Subcommand)]
+enum FriendsAction {
+    #[command(about = "フレンドを追加します。")]
+    Add {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    #[command(alias = "rm", about = "フレンドを削除します。")]
+    Remove {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    #[command(alias = "ls", about = "フレンドの一覧とオンライン状況を表示します。")]
+    List,
+    #[command(about = "フレンドがオンラインになったら通知します。")]
+    Watch {
+        #[arg(long, default_value_t = 5, help = "ステータスを取得する間隔 (秒)")]
+        interval: u64,
+        #[arg(long, help = "通知時に実行するコマンド (設定ファイルの friend_hook より優先)")]
+        hook: Option<String>,
+    },
+}
+
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// API が接続を受け付けたまま応答しない場合でも、次の取得元に切り替えられるようにタイムアウトを付ける
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(HTTP_CONNECT_TIMEOUT)
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+async fn fetch_haven_status(target: &ServerProfile) -> Result<HavenStatus, Box<dyn std::error::Error>> {
+    let url: &str = target.api_url.as_deref().ok_or_else(|| HaoleError::config("このサーバーには HavenMC API が設定されていません。"))?;
+    let resp: HavenStatus = http_client().get(url).send().await?.json().await?;
     Ok(resp)
 }
 
-async fn fetch_haven_status_by_mcstatusio() -> Result<McStatusIOResponse, Box<dyn std::error::Error>> {
-    let url: &str = "https://api.mcstatus.io/v2/status/java/play.havenmc.jp";
-    let resp: McStatusIOResponse = reqwest::get(url).await?.json().await?;
+async fn fetch_bedrock_status(target: &ServerProfile) -> Result<bedrock::BedrockStatus, Box<dyn std::error::Error>> {
+    bedrock::status(&target.host, target.bedrock_port).await
+}
+
+fn print_player_list(list: Option<Vec<String>>) {
+    if let Some(list) = list {
+        if list.is_empty() {
+            println!("{}", "現在オンラインのプレイヤーはいません。".yellow());
+        } else {
+            for player in list {
+                println!(" - {}", player.cyan());
+            }
+        }
+    } else {
+        println!("{}", "プレイヤー名の取得が制限されているか、データがありません。".red());
+    }
+}
+
+async fn with_query_fallback(target: &ServerProfile, list: Option<Vec<String>>) -> Option<Vec<String>> {
+    if list.is_some() {
+        return list;
+    }
+    query::full_stat(&target.host, target.port).await.ok().map(|st: query::QueryStatus| st.players)
+}
+
+async fn query_server(host: &str, port: u16) -> Result<slp::SlpResponse, Box<dyn std::error::Error>> {
+    let modern_err: Box<dyn std::error::Error> = match slp::status(host, port).await {
+        Ok(resp) => return Ok(resp),
+        Err(e) => e,
+    };
+    // 接続できなかった場合は、旧形式で試しても同じなのでそのまま返す
+    if !slp::is_handshake_failure(modern_err.as_ref()) {
+        return Err(modern_err);
+    }
+    for format in [legacy::LegacyFormat::V1_6, legacy::LegacyFormat::Beta] {
+        if let Ok(resp) = legacy::status(format, host, port).await {
+            return Ok(resp);
+        }
+    }
+    Err(modern_err)
+}
+
+async fn fetch_status(target: &ServerProfile, recorder: Option<&Recorder>) -> Result<provider::Answer, Box<dyn std::error::Error>> {
+    let result: Result<provider::Answer, Box<dyn std::error::Error>> = provider::fetch(target).await;
+    if let Some(recorder) = recorder {
+        recorder.record(target, &result);
+    }
+    result
+}
+
+fn print_source(source: provider::Source) {
+    println!("{}", format!("(取得元: {})", source.label()).dimmed());
+}
+
+fn sample_of(target: &ServerProfile, result: &Result<provider::Answer, Box<dyn std::error::Error>>) -> history::Sample {
+    match result {
+        Ok(answer) => history::Sample::from_status(&target.name, &answer.status, answer.source, answer.latency),
+        Err(e) => history::Sample::failed(&target.name, e.as_ref()),
+    }
+}
+
+async fn record_history(target: &ServerProfile, store: &history::Store) -> (Option<provider::Answer>, history::Sample) {
+    let result: Result<provider::Answer, Box<dyn std::error::Error>> = provider::fetch(target).await;
+    let sample: history::Sample = sample_of(target, &result);
+    let _ = store.append(&sample);
+    (result.ok(), sample)
+}
+
+// --watch 中、コマンドが取得したステータスをそのまま履歴に記録する
+struct Recorder {
+    store: history::Store,
+    last: std::cell::RefCell<Option<history::Sample>>,
+}
+
+impl Recorder {
+    fn record(&self, target: &ServerProfile, result: &Result<provider::Answer, Box<dyn std::error::Error>>) {
+        let sample: history::Sample = sample_of(target, result);
+        let _ = self.store.append(&sample);
+        *self.last.borrow_mut() = Some(sample);
+    }
+
+    // ステータスを取得しないコマンドでは None
+    fn take(&self) -> Option<history::Sample> {
+        self.last.borrow_mut().take()
+    }
+}
+
+async fn fetch_haven_status_by_mcstatusio(target: &ServerProfile) -> Result<McStatusIOResponse, Box<dyn std::error::Error>> {
+    let url: String = target.mcstatus_url();
+    let resp: McStatusIOResponse = http_client().get(&url).send().await?.json().await?;
     Ok(resp)
 }
 
-fn not_reccommended() {
-    println!("{}", "!! このコマンドは現在推奨されていません。不安定な動作をする、もしくは機能しない可能性があります。\n".yellow());
+async fn fetch_haven_status_by_slp(target: &ServerProfile) -> Result<McStatusIOResponse, Box<dyn std::error::Error>> {
+    let resp: slp::SlpResponse = query_server(&target.host, target.port).await?;
+    Ok(McStatusIOResponse::from_slp(&target.host, &resp))
 }
 
-fn under_dev() {
-    println!("{}", "!! この機能は現在開発中です。不安定な動作をする、もしくは機能しない可能性があります。\n".yellow());
+async fn fetch_motd(target: &ServerProfile, edition: Edition) -> Result<(Vec<motd::Span>, Option<provider::Source>), Box<dyn std::error::Error>> {
+    if edition == Edition::Bedrock {
+        return Ok((motd::parse_legacy(&fetch_bedrock_status(target).await?.motd.join("\n")), None));
+    }
+    let (source, details) = provider::fetch_details(target).await?;
+    // 直接問い合わせなら JSON コンポーネントの16進カラーを保てる
+    let spans: Vec<motd::Span> = match details {
+        provider::Details::Slp(resp) => motd::parse_component(&resp.status.description),
+        provider::Details::Mcstatus(resp) => motd::parse_legacy(&resp.motd.raw),
+    };
+    Ok((spans, Some(source)))
+}
+
+async fn fetch_icon(target: &ServerProfile) -> Result<(Option<icon::Icon>, provider::Source), Box<dyn std::error::Error>> {
+    let (source, details) = provider::fetch_details(target).await?;
+    let uri: Option<String> = match details {
+        provider::Details::Slp(resp) => resp.status.favicon,
+        provider::Details::Mcstatus(resp) => resp.icon,
+    };
+    Ok((uri.map(|u: String| icon::Icon::from_data_uri(&u)).transpose()?, source))
 }
+
+async fn fetch_server_info(target: &ServerProfile) -> Result<(provider::Source, McStatusIOResponse), Box<dyn std::error::Error>> {
+    let (source, details) = provider::fetch_details(target).await?;
+    let resp: McStatusIOResponse = match details {
+        provider::Details::Slp(resp) => McStatusIOResponse::from_slp(&target.host, &resp),
+        provider::Details::Mcstatus(resp) => resp,
+    };
+    Ok((source, resp))
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> ExitCode {
     let default_panic: Box<dyn Fn(&std::panic::PanicHookInfo<'_>) + Send + Sync> = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
-        let _ = crossterm::terminal::disable_raw_mode();
-        let _ = execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
+        tui::restore_terminal();
         default_panic(info);
     }));
 
-    let args: Vec<String> = std::env::args().collect();
-    let cfg: HaoleConfig = confy::load("haole", "config").map_err(|e: confy::ConfyError| {
+    match run().await {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("{} エラー: {}", "!!".red(), e);
+            ExitCode::from(error::exit_code(e.as_ref()))
+        }
+    }
+}
+
+async fn run() -> Result<u8, Box<dyn std::error::Error>> {
+    let cfg: HaoleConfig = HaoleConfig::load().map_err(|e: confy::ConfyError| {
         eprintln!("{} 設定ファイルの読み込みに失敗しました。デフォルト値を使用します: {}", "!!".yellow(), e);
     }).unwrap_or_default();
-    if cfg.mode == "tui" && args.len() == 1 {
-        run_tui_loop().await?;
-        return Ok(());
-    }
-        let cli: Cli = Cli::parse();
+        let cli: Cli = match Cli::try_parse() {
+            Ok(cli) => cli,
+            Err(e) => {
+                let _ = e.print();
+                return Ok(if e.use_stderr() { EXIT_CONFIG } else { EXIT_OK });
+            }
+        };
+        let target: ServerProfile = cfg.resolve(cli.server.as_deref(), cli.host.as_deref())?;
+        let open_tui: bool = match cli.command {
+            Some(Commands::Tui) => true,
+            None => cfg.mode == "tui",
+            Some(_) => false,
+        };
+        if open_tui {
+            tui::run(&target, &cfg.friends).await?;
+            return Ok(EXIT_OK);
+        }
+        if cli.command.is_none() {
+            Cli::command().print_help()?;
+            return Ok(EXIT_OK);
+        }
         let interval_secs: u64 = cli.watch
             .map(|inner: Option<u64>| inner.unwrap_or(5))
             .map(|sec: u64| sec.max(2))
@@ -167,96 +503,163 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         if interval_secs > 0 {
             let mut stdout: std::io::Stdout = stdout();
-            
+            let recorder: Recorder = Recorder { store: history::Store::open(&target.name)?, last: std::cell::RefCell::new(None) };
+            let mut tracker: events::EventTracker = events::EventTracker::default();
+            let mut recent: VecDeque<events::PlayerEvent> = VecDeque::with_capacity(10);
+
             loop {
-                execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
-                println!("{} {}秒おきに監視中... (Qキーで終了)\n", ">>".blue(), interval_secs);
+                if !cli.output.is_structured() && !cli.quiet {
+                    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+                    println!("{} {}秒おきに監視中... (Qキーで終了)\n", ">>".blue(), interval_secs);
+                }
 
-                if let Err(e) = run_app(&cli).await {
-                    println!("{} エラー: {}", "!!".red(), e);
+                if let Err(e) = run_app(&cli, &target, Some(&recorder)).await {
+                    eprintln!("{} エラー: {}", "!!".red(), e);
+                }
+                if let Some(sample) = recorder.take() {
+                    for e in tracker.update(&sample) {
+                        if recent.len() >= 10 {
+                            recent.pop_front();
+                        }
+                        recent.push_back(e);
+                    }
+                }
+                if !cli.output.is_structured() && !cli.quiet && !recent.is_empty() {
+                    println!();
+                    for e in &recent {
+                        println!("{}", e.colored_line());
+                    }
                 }
 
                 if event::poll(Duration::from_secs(interval_secs))? {
                     if let Event::Key(key) = event::read()? {
-                        if key.kind == KeyEventKind::Press {
-                            if key.code == KeyCode::Char('q') || key.code == KeyCode::Char('Q') {
+                        if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || key.code == KeyCode::Char('Q')) {
+                            if !cli.quiet {
                                 println!("\n{}", "監視を終了しました。".yellow());
-                                break;
                             }
+                            break;
                         }
                     }
                 }
             }
+            Ok(EXIT_OK)
         } else {
-            run_app(&cli).await?;
+            run_app(&cli, &target, None).await
         }
-
-    Ok(())
 }
 
-async fn run_app(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
-    match &cli.command {
+async fn run_app(cli: &Cli, target: &ServerProfile, recorder: Option<&Recorder>) -> Result<u8, Box<dyn std::error::Error>> {
+    let Some(command) = &cli.command else {
+        return Ok(EXIT_OK);
+    };
+    let format: OutputFormat = if cli.quiet { OutputFormat::Quiet } else { cli.output };
+    let emit = |command: &str, data: serde_json::Value| {
+        output::emit(format, command, &target.name, data).map(|_| EXIT_OK)
+    };
+    match command {
         Commands::Author => {
+            if format.is_structured() {
+                return emit("author", json!({ "author": "KoHaRxnP" }));
+            }
             println!("Created by: {}", "KoHaRxnP".magenta());
-            return Ok(());
+            Ok(EXIT_OK)
         }
         Commands::Players => {
-            let st: HavenStatus = fetch_haven_status().await?;
-            if let Some(list) = st.players.list {
-                if list.is_empty() {
-                    println!("{}", "現在オンラインのプレイヤーはいません。".yellow());
-                } else {
-                    for player in list {
-                        println!(" - {}", player.cyan());
-                    }
-                }
-            } else {
-                println!("{}", "プレイヤー名の取得が制限されているか、データがありません。".red());
+            let provider::Answer { source, status: st, .. } = fetch_status(target, recorder).await?;
+            let list: Option<Vec<String>> = with_query_fallback(target, st.players.list).await;
+            if format.is_structured() {
+                return emit("players", json!({ "players": list, "source": source }));
             }
-            return Ok(());
+            print_player_list(list);
+            print_source(source);
+            Ok(EXIT_OK)
         }
-        Commands::Pq => {
-            let st: HavenStatus = fetch_haven_status().await?;
+        Commands::Pq { edition } if edition.unwrap_or(target.edition) == Edition::Bedrock => {
+            let st: bedrock::BedrockStatus = fetch_bedrock_status(target).await?;
+            if format.is_structured() {
+                return emit("pq", json!({ "edition": Edition::Bedrock, "online": st.online, "max": st.max }));
+            }
+            println!("\n{} {}/{} プレイヤーがオンライン (Bedrock)", 
+                "●".green(), st.online, st.max);
+            Ok(EXIT_OK)
+        }
+        Commands::Pq { .. } => {
+            let provider::Answer { source, status: st, .. } = fetch_status(target, recorder).await?;
+            if format.is_structured() {
+                return emit("pq", json!({ "edition": Edition::Java, "online": st.players.online, "max": st.players.max, "source": source }));
+            }
             println!("\n{} {}/{} プレイヤーがオンライン", 
                 "●".green(), st.players.online, st.players.max);
-            return Ok(());
+            print_source(source);
+            Ok(EXIT_OK)
         }
         Commands::Pall => {
-            let st: HavenStatus = fetch_haven_status().await?;
-            if let Some(list) = st.players.list {
-                if list.is_empty() {
-                    println!("{}", "現在オンラインのプレイヤーはいません。".yellow());
-                } else {
-                    for player in list {
-                        println!(" - {}", player.cyan());
-                    }
-                }
-            } else {
-                println!("{}", "プレイヤー名の取得が制限されているか、データがありません。".red());
+            let provider::Answer { source, status: st, .. } = fetch_status(target, recorder).await?;
+            let list: Option<Vec<String>> = with_query_fallback(target, st.players.list).await;
+            if format.is_structured() {
+                return emit("pall", json!({
+                    "edition": Edition::Java,
+                    "online": st.players.online,
+                    "max": st.players.max,
+                    "players": list,
+                    "source": source,
+                }));
             }
+            print_player_list(list);
             println!("\n{} {}/{} プレイヤーがオンライン", 
                 "●".green(), st.players.online, st.players.max);
-            return Ok(());
+            print_source(source);
+            Ok(EXIT_OK)
+        }
+        Commands::Info => {
+            info::run(target, format).await?;
+            Ok(EXIT_OK)
+        }
+        Commands::IsOnline { edition } if edition.unwrap_or(target.edition) == Edition::Bedrock => {
+            let online: bool = fetch_bedrock_status(target).await.is_ok();
+            let code: u8 = if online { EXIT_OK } else { EXIT_FALSE };
+            if format.is_structured() {
+                return emit("is-online", json!({ "edition": Edition::Bedrock, "online": online })).map(|_| code);
+            }
+            if online {
+                println!("{}", "サーバーはオンラインです。(Bedrock)".green());
+            } else {
+                println!("{}", " サーバーはオフラインです。(Bedrock)".red());
+            }
+            Ok(code)
         }
-        Commands::IsOnline => {
-            let st: HavenStatus = fetch_haven_status().await?;
-            if st.online == true {
+        Commands::IsOnline { .. } => {
+            let provider::Answer { source, status: st, .. } = fetch_status(target, recorder).await?;
+            let code: u8 = if st.online { EXIT_OK } else { EXIT_FALSE };
+            if format.is_structured() {
+                return emit("is-online", json!({ "edition": Edition::Java, "online": st.online, "source": source })).map(|_| code);
+            }
+            if st.online {
                 println!("{}", "サーバーはオンラインです。".green());
             } else {
                 println!("{}", " サーバーはオフラインです。".red());
             }
-            return Ok(());
+            print_source(source);
+            Ok(code)
         }
         Commands::IsOffline => {
-            let st: HavenStatus = fetch_haven_status().await?;
-            if st.online == false {
+            let provider::Answer { source, status: st, .. } = fetch_status(target, recorder).await?;
+            let code: u8 = if !st.online { EXIT_OK } else { EXIT_FALSE };
+            if format.is_structured() {
+                return emit("is-offline", json!({ "edition": Edition::Java, "offline": !st.online, "source": source })).map(|_| code);
+            }
+            if !st.online {
                 println!("{}", "サーバーはオフラインです。".green());
             } else {
                 println!("{}", "サーバーはオンラインです。".red());
             }
-            return Ok(());
+            print_source(source);
+            Ok(code)
         }
         Commands::Version => {
+            if format.is_structured() {
+                return emit("version", json!({ "version": env!("CARGO_PKG_VERSION") }));
+            }
             let logo: &str = r#"
                           _                _
             ░▒▓█▓▒░       | |__   __ _  ___| | ___       ░▒▓█▓▒░
@@ -268,64 +671,126 @@ async fn run_app(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
             let version: &str = env!("CARGO_PKG_VERSION");
             println!("{}", logo.green().bold());
             println!("Haole Version: {}", version.magenta());
-            return Ok(());
+            Ok(EXIT_OK)
         }
-        Commands::ServerVersion => {
-            let st: HavenStatus = fetch_haven_status().await?;
+        Commands::ServerVersion { edition } if edition.unwrap_or(target.edition) == Edition::Bedrock => {
+            let st: bedrock::BedrockStatus = fetch_bedrock_status(target).await?;
+            if format.is_structured() {
+                return emit("server-version", json!({
+                    "edition": Edition::Bedrock,
+                    "version": st.version,
+                    "protocol": st.protocol,
+                    "server_edition": st.edition,
+                    "game_mode": st.game_mode,
+                    "server_guid": st.server_guid,
+                }));
+            }
+            println!("Server Version: {} ({})", st.version.magenta(), st.edition);
+            println!("Protocol Version: {}", st.protocol.to_string().magenta());
+            if let Some(mode) = st.game_mode {
+                println!("Game Mode: {}", mode.magenta());
+            }
+            println!("Server GUID: {}", st.server_guid.magenta());
+            Ok(EXIT_OK)
+        }
+        Commands::ServerVersion { .. } => {
+            let provider::Answer { source, status: st, .. } = fetch_status(target, recorder).await?;
+            if format.is_structured() {
+                return emit("server-version", json!({ "edition": Edition::Java, "version": st.version, "source": source }));
+            }
             println!("Server Version: {}", st.version.magenta());
-            return Ok(());
+            print_source(source);
+            Ok(EXIT_OK)
         }
         Commands::Ip => {
-            let st_mcstatusio: McStatusIOResponse = fetch_haven_status_by_mcstatusio().await?;
+            let (source, st_mcstatusio) = fetch_server_info(target).await?;
+            if format.is_structured() {
+                return emit("ip", json!({ "ip_address": st_mcstatusio.ip_address, "source": source }));
+            }
             println!("Server IP: {}", st_mcstatusio.ip_address.magenta());
-            return Ok(());
+            print_source(source);
+            Ok(EXIT_OK)
         }
         Commands::Host => {
-            let st_mcstatusio: McStatusIOResponse = fetch_haven_status_by_mcstatusio().await?;
+            let (source, st_mcstatusio) = fetch_server_info(target).await?;
+            if format.is_structured() {
+                return emit("host", json!({ "host": st_mcstatusio.host, "source": source }));
+            }
             println!("Server Host: {}", st_mcstatusio.host.magenta());
-            return Ok(());
+            print_source(source);
+            Ok(EXIT_OK)
         }
         Commands::Protocol => {
-            let st_mcstatusio: McStatusIOResponse = fetch_haven_status_by_mcstatusio().await?;
+            let (source, st_mcstatusio) = fetch_server_info(target).await?;
+            if format.is_structured() {
+                return emit("protocol", json!({ "protocol": st_mcstatusio.version.protocol, "source": source }));
+            }
             println!("Protocol Version: {}", st_mcstatusio.version.protocol.to_string().magenta());
-            return Ok(());
+            print_source(source);
+            Ok(EXIT_OK)
         }
         Commands::Port => {
-            let st_mcstatusio: McStatusIOResponse = fetch_haven_status_by_mcstatusio().await?;
+            let (source, st_mcstatusio) = fetch_server_info(target).await?;
+            if format.is_structured() {
+                return emit("port", json!({ "port": st_mcstatusio.port, "source": source }));
+            }
             println!("Server Port: {}", st_mcstatusio.port.to_string().magenta());
-            return Ok(());
-        }
-        Commands::Motd { raw, clean, html } => {
-            let st_mcstatusio: McStatusIOResponse = fetch_haven_status_by_mcstatusio().await?;
-            if let Some(_query) = raw {
-                println!("MOTD (Raw): {}", st_mcstatusio.motd.raw.magenta());
-            } else if let Some(_query) = clean {
-                println!("MOTD (Clean): {}", st_mcstatusio.motd.clean.magenta());
-            } else if let Some(_query) = html {
-                println!("MOTD (HTML): {}", st_mcstatusio.motd.html.magenta());
-            } else {
-                println!("MOTD: {}", st_mcstatusio.motd.clean.magenta());
+            print_source(source);
+            Ok(EXIT_OK)
+        }
+        Commands::Motd { format: motd_format, render, edition } => {
+            let edition: Edition = edition.unwrap_or(target.edition);
+            let (spans, source) = fetch_motd(target, edition).await?;
+            if format.is_structured() {
+                return emit("motd", json!({
+                    "edition": edition,
+                    "raw": motd::to_raw(&spans),
+                    "clean": motd::to_clean(&spans),
+                    "html": motd::to_html(&spans),
+                    "markdown": motd::to_markdown(&spans),
+                    "component": motd::to_json(&spans),
+                    "source": source,
+                }));
+            }
+            let motd_format: Option<motd::MotdFormat> = if *render { Some(motd::MotdFormat::Ansi) } else { *motd_format };
+            match motd_format {
+                Some(f) => println!("{}", motd::render(&spans, f)),
+                None => {
+                    println!("MOTD: {}", motd::to_clean(&spans).magenta());
+                    // --format の出力はファイルに保存されることがあるため、取得元は添えない
+                    if let Some(source) = source {
+                        print_source(source);
+                    }
+                }
             }
-            return Ok(());
+            Ok(EXIT_OK)
         }
         Commands::Mode { new_mode } => {
-            let mut cfg: HaoleConfig = confy::load("haole", "config")?;
+            let mut cfg: HaoleConfig = HaoleConfig::load()?;
             if let Some(m) = new_mode {
                 if m == "cli" || m == "tui" {
                     cfg.mode = m.to_string(); 
-                    confy::store("haole", "config", &cfg)?;
-                    println!("{} モードを {} に変更しました。", ">>".green(), cfg.mode.cyan());
+                    cfg.store()?;
                 } else if m == "toggle" {
                     cfg.mode = if cfg.mode == "cli" { "tui".to_string() } else { "cli".to_string() };
-                    confy::store("haole", "config", &cfg)?;
-                    println!("{} モードを {} に変更しました。", ">>".green(), cfg.mode.cyan());
+                    cfg.store()?;
+                } else if format.is_structured() {
+                    return Err(HaoleError::config(format!("無効なモードです: {}", m)));
                 } else {
                     println!("{} 無効なモードです。cli または tui を指定してください。toggleで切り替えることもできます。", "!!".red());
+                    return Ok(EXIT_CONFIG);
                 }
+                if format.is_structured() {
+                    return emit("mode", json!({ "mode": cfg.mode, "changed": true }));
+                }
+                println!("{} モードを {} に変更しました。", ">>".green(), cfg.mode.cyan());
             } else {
+                if format.is_structured() {
+                    return emit("mode", json!({ "mode": cfg.mode, "changed": false }));
+                }
                 println!("現在のモード: {}", cfg.mode.cyan());
             }
-            return Ok(());
+            Ok(EXIT_OK)
         }
         Commands::Update => {
             println!("{} 最新バージョンを確認中...", ">>".blue());
@@ -351,160 +816,265 @@ async fn run_app(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 Err(e) => println!("{} アップデート中にエラーが発生しました: {}", "!!".red(), e),
             }
-            return Ok(());
+            Ok(EXIT_OK)
         }
         Commands::Ping => {
-            not_reccommended();
-            under_dev();
-            run_ping().await?;
-            return Ok(());
+            run_ping(target, format).await?;
+            Ok(EXIT_OK)
+        }
+        Commands::Query { port } => {
+            let st: query::QueryStatus = query::full_stat(&target.host, port.unwrap_or(target.port)).await?;
+            if format.is_structured() {
+                return emit("query", json!({
+                    "motd": st.motd,
+                    "version": st.version,
+                    "game_type": st.game_type,
+                    "software": st.software,
+                    "map": st.map,
+                    "host_ip": st.host_ip,
+                    "host_port": st.host_port,
+                    "plugins": st.plugins,
+                    "online": st.online,
+                    "max": st.max,
+                    "players": st.players,
+                }));
+            }
+            println!("MOTD: {}", st.motd.magenta());
+            println!("Server Version: {} ({})", st.version.magenta(), st.game_type);
+            println!("Software: {}", st.software.magenta());
+            println!("Map: {}", st.map.magenta());
+            println!("Address: {}:{}", st.host_ip, st.host_port);
+            if st.plugins.is_empty() {
+                println!("Plugins: {}", "なし".yellow());
+            } else {
+                println!("Plugins ({}):", st.plugins.len());
+                for plugin in st.plugins {
+                    println!(" - {}", plugin.cyan());
+                }
+            }
+            println!("\n{} {}/{} プレイヤーがオンライン", 
+                "●".green(), st.online, st.max);
+            for player in st.players {
+                println!(" - {}", player.cyan());
+            }
+            Ok(EXIT_OK)
+        }
+        Commands::Servers => {
+            let cfg: HaoleConfig = HaoleConfig::load()?;
+            let path: std::path::PathBuf = confy::get_configuration_file_path("haole", "config")?;
+            if format.is_structured() {
+                return emit("servers", json!({
+                    "default_server": cfg.default_server,
+                    "config_path": path,
+                    "servers": cfg.servers.iter().map(|(key, p)| json!({
+                        "key": key,
+                        "name": p.name,
+                        "host": p.host,
+                        "port": p.port,
+                        "bedrock_port": p.bedrock_port,
+                        "edition": p.edition,
+                        "api_url": p.api_url,
+                        "providers": p.providers,
+                    })).collect::<Vec<serde_json::Value>>(),
+                }));
+            }
+            for (key, p) in &cfg.servers {
+                let marker: ColoredString = if *key == cfg.default_server { "*".green() } else { " ".normal() };
+                println!("{} {} ({}) {}:{} [{:?}]", marker, key.cyan(), p.name, p.host, p.port, p.edition);
+            }
+            println!("\n設定ファイル: {}", path.display());
+            Ok(EXIT_OK)
+        }
+        Commands::Exporter { listen, interval } => {
+            exporter::run(target, *listen, Duration::from_secs((*interval).max(1))).await?;
+            Ok(EXIT_OK)
+        }
+        Commands::Events { since, follow, interval } => {
+            events::run(target, since, *follow, Duration::from_secs((*interval).max(2)), format).await?;
+            Ok(EXIT_OK)
+        }
+        Commands::Icon { save, show } => {
+            let (icon, source) = fetch_icon(target).await?;
+            let Some(icon) = icon else {
+                if !format.is_structured() {
+                    println!("{} このサーバーにはアイコンが設定されていません。", "!!".yellow());
+                    print_source(source);
+                }
+                return Ok(EXIT_FALSE);
+            };
+            if let Some(path) = save {
+                std::fs::write(path, &icon.png)?;
+                if !format.is_structured() {
+                    println!("{} {} に保存しました ({}x{})", ">>".green(), path.display(), icon.width, icon.height);
+                }
+            }
+            if format.is_structured() {
+                return emit("icon", json!({
+                    "width": icon.width,
+                    "height": icon.height,
+                    "saved": save.as_ref().map(|p: &std::path::PathBuf| p.display().to_string()),
+                    "source": source,
+                }));
+            }
+            if *show || save.is_none() {
+                print!("{}", icon.to_ansi(icon.width.min(64)));
+            }
+            print_source(source);
+            Ok(EXIT_OK)
+        }
+        Commands::Friends { action } => run_friends(target, action, format, recorder).await,
+        // TUI は run() で起動するため、ここには来ない
+        Commands::Tui => Ok(EXIT_OK),
+        Commands::Stats { since } => {
+            let found: bool = stats::run(target, since, format)?;
+            Ok(if found { EXIT_OK } else { EXIT_FALSE })
+        }
+        Commands::Heatmap { weeks } => {
+            let found: bool = heatmap::run(target, *weeks, format)?;
+            Ok(if found { EXIT_OK } else { EXIT_FALSE })
+        }
+        Commands::Incidents { since } => {
+            let found: bool = incidents::run(target, since, format)?;
+            Ok(if found { EXIT_OK } else { EXIT_FALSE })
         }
     }
 }
 
-async fn run_tui_loop() -> Result<(), Box<dyn std::error::Error>> {
-    let mut stdout: std::io::Stdout = std::io::stdout();
-    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
-    crossterm::terminal::enable_raw_mode()?;
-    let mut stdout: std::io::Stdout = std::io::stdout();
-    execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
-
-    let _ = crossterm::terminal::disable_raw_mode();
-    let _ = execute!(stdout, crossterm::terminal::LeaveAlternateScreen);
-    
-    let backend: CrosstermBackend<std::io::Stdout> = CrosstermBackend::new(stdout);
-    let mut terminal: Terminal<CrosstermBackend<std::io::Stdout>> = Terminal::new(backend)?;
-
-    terminal.clear()?;
-
-    let mut last_tick: std::time::Instant = std::time::Instant::now();
-    let tick_rate: Duration = Duration::from_secs(5);
-
-    let mut st: Option<HavenStatus> = fetch_haven_status().await.ok();
-
-    let mut history: VecDeque<HistoryEntry> = VecDeque::with_capacity(50);
-
-    loop {
-        terminal.draw(|f: &mut ratatui::Frame<'_>| {
-            let size: ratatui::prelude::Rect = f.area();
-
-            let chunks: std::rc::Rc<[ratatui::prelude::Rect]> = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(3),
-                    Constraint::Min(0),
-                ])
-                .split(size);
-
-            let main_layout: std::rc::Rc<[ratatui::prelude::Rect]> = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(30),
-                    Constraint::Percentage(70),
-                ])
-                .split(chunks[1]);
-
-            let status_text: String = match &st {
-                Some(s) => {
-                    format!(" サーバー: {} | オンライン: {}/{}", 
-                    if s.online { "ONLINE".green() } else { "OFFLINE".red() },
-                    s.players.online, s.players.max)
-                },
-                None => format!(" {} データを取得中、または接続エラー...", "!!".yellow())
-            };
-            let status_bar: Paragraph<'_> = Paragraph::new(status_text)
-                .block(Block::default().borders(Borders::ALL).title(" HavenMC Status "));
-
-            let players_items: Vec<ListItem> = if let Some(ref s) = st {
-                s.players.list.as_ref().map_or(vec![], |list: &Vec<String>| {
-                    list.iter().map(|p: &String| ListItem::new(format!("  • {}", p))).collect()
-                })
-            } else { vec![] };
-            let players_list: List<'_> = List::new(players_items)
-                .block(Block::default().borders(Borders::ALL).title(" Players "));
-
-            let right_chunks: std::rc::Rc<[ratatui::prelude::Rect]> = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(3),
-                    Constraint::Min(0),
-                ])
-                .split(main_layout[1]);
-
-            let data: Vec<u64> = history.iter().map(|e: &HistoryEntry| e.online as u64).collect();
-            
-            let cmax: u64 = data.iter().max().cloned().unwrap_or(0);
-            let max: u64 = if cmax < 10 { 10 } else { cmax + 5 };
-            let sparkline: ratatui::widgets::Sparkline<'_> = ratatui::widgets::Sparkline::default()
-                .block(Block::default().borders(Borders::LEFT | Borders::RIGHT | Borders::TOP).title(" Activity "))
-                .data(&data)
-                .max(max)
-                .style(ratatui::style::Style::default().fg(ratatui::style::Color::Cyan));
-
-            let history_content: Vec<ListItem> = history.iter().rev()
-                .map(|e: &HistoryEntry| ListItem::new(format!(" [{}] {} players", e.time, e.online)))
-                .collect();
-            let history_list: List<'_> = List::new(history_content)
-                .block(Block::default().borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM));
-
-            f.render_widget(status_bar, chunks[0]);
-            f.render_widget(players_list, main_layout[0]);
-            f.render_widget(sparkline, right_chunks[0]);
-            f.render_widget(history_list, right_chunks[1]);
-        })?;
-
-        let timeout: Duration = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or(Duration::from_secs(0));
-
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = crossterm::event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    if key.code == KeyCode::Char('q') {
-                        break;
+async fn run_friends(target: &ServerProfile, action: &FriendsAction, format: OutputFormat, recorder: Option<&Recorder>) -> Result<u8, Box<dyn std::error::Error>> {
+    let mut cfg: HaoleConfig = HaoleConfig::load()?;
+    match action {
+        FriendsAction::Add { names } => {
+            for name in names {
+                if friends::is_friend(&cfg.friends, name) {
+                    if !format.is_structured() {
+                        println!("{} {} はすでに登録されています。", "!!".yellow(), name.cyan());
+                    }
+                } else {
+                    cfg.friends.push(name.clone());
+                    if !format.is_structured() {
+                        println!("{} {} を追加しました。", ">>".green(), name.cyan());
                     }
                 }
             }
+            cfg.store()?;
+            if format.is_structured() {
+                output::emit(format, "friends", &target.name, json!({ "friends": cfg.friends }))?;
+            }
+            Ok(EXIT_OK)
         }
-
-        if last_tick.elapsed() >= tick_rate {
-            st = fetch_haven_status().await.ok();
-
-            if let Some(ref s) = st {
-                if history.len() >= 50 {
-                    history.pop_front();
+        FriendsAction::Remove { names } => {
+            let mut code: u8 = EXIT_OK;
+            for name in names {
+                if friends::is_friend(&cfg.friends, name) {
+                    cfg.friends.retain(|f: &String| !f.eq_ignore_ascii_case(name));
+                    if !format.is_structured() {
+                        println!("{} {} を削除しました。", ">>".green(), name.cyan());
+                    }
+                } else {
+                    if !format.is_structured() {
+                        println!("{} {} は登録されていません。", "!!".yellow(), name.cyan());
+                    }
+                    code = EXIT_FALSE;
                 }
-                history.push_back(HistoryEntry {
-                    time: Local::now().format("%H:%M:%S").to_string(),
-                    online: s.players.online,
-                });
             }
-            last_tick = std::time::Instant::now();
+            cfg.store()?;
+            if format.is_structured() {
+                output::emit(format, "friends", &target.name, json!({ "friends": cfg.friends }))?;
+            }
+            Ok(code)
+        }
+        FriendsAction::List => {
+            let online: Option<Vec<String>> = match fetch_status(target, recorder).await {
+                Ok(answer) => with_query_fallback(target, answer.status.players.list).await,
+                Err(_) => None,
+            };
+            let is_online = |name: &String| online.as_ref().map(|list: &Vec<String>| friends::is_friend(list, name));
+            if format.is_structured() {
+                return output::emit(format, "friends", &target.name, json!({
+                    "friends": cfg.friends.iter().map(|f: &String| json!({ "name": f, "online": is_online(f) })).collect::<Vec<serde_json::Value>>(),
+                })).map(|_| EXIT_OK);
+            }
+            if cfg.friends.is_empty() {
+                println!("{} フレンドが登録されていません。haole friends add <MCID> で追加できます。", "!!".yellow());
+                return Ok(EXIT_OK);
+            }
+            for f in &cfg.friends {
+                match is_online(f) {
+                    Some(true) => println!(" {} {}", "●".green(), f.cyan()),
+                    Some(false) => println!(" {} {}", "○".normal(), f),
+                    None => println!(" {} {}", "?".yellow(), f),
+                }
+            }
+            Ok(EXIT_OK)
+        }
+        FriendsAction::Watch { interval, hook } => {
+            if cfg.friends.is_empty() {
+                return Err(HaoleError::config("フレンドが登録されていません。haole friends add <MCID> で追加してください。"));
+            }
+            let hook: Option<&str> = hook.as_deref().or(cfg.friend_hook.as_deref());
+            friends::watch(target, &cfg.friends, hook, Duration::from_secs((*interval).max(2)), format).await?;
+            Ok(EXIT_OK)
         }
     }
-
-    crossterm::terminal::disable_raw_mode()?;
-    execute!(terminal.backend_mut(), crossterm::terminal::LeaveAlternateScreen)?;
-    Ok(())
 }
 
-async fn run_ping() -> Result<(), Box<dyn std::error::Error>> {
-    println!("{} play.havenmc.jp へ Ping を送信中...", ">>".blue());
+async fn run_ping(target: &ServerProfile, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    if !format.is_structured() {
+        println!("{} {}:{} へ Ping を送信中...\n", ">>".blue(), target.host, target.port);
+    }
+
+    let count: u32 = 4;
+    let mut rtts: Vec<f64> = Vec::new();
+    let mut samples: Vec<Option<f64>> = Vec::new();
+    let mut address: Option<String> = None;
+    for seq in 1..=count {
+        match query_server(&target.host, target.port).await {
+            Ok(resp) => {
+                let ms: f64 = resp.latency.as_secs_f64() * 1000.0;
+                if !format.is_structured() {
+                    println!("{} からの応答: seq={} 時間={:.1}ms", resp.address.to_string().cyan(), seq, ms);
+                }
+                address = Some(resp.address.to_string());
+                rtts.push(ms);
+                samples.push(Some(ms));
+            }
+            Err(e) => {
+                if !format.is_structured() {
+                    println!("{} seq={} 応答がありません: {}", "!!".red(), seq, e);
+                }
+                samples.push(None);
+            }
+        }
+        if seq < count {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
 
-    let count_flag: &str = if cfg!(windows) { "-n" } else { "-c" };
+    let lost: u32 = count - rtts.len() as u32;
+    let min: Option<f64> = rtts.iter().cloned().reduce(f64::min);
+    let max: Option<f64> = rtts.iter().cloned().reduce(f64::max);
+    let avg: Option<f64> = (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / rtts.len() as f64);
 
-    let output = Command::new("ping")
-        .args([count_flag, "4", "play.havenmc.jp"])
-        .output()
-        .map_err(|e| format!("外部コマンド 'ping' の実行に失敗しました。パスが通っているか確認してください: {}", e))?;
+    if format.is_structured() {
+        return output::emit(format, "ping", &target.name, json!({
+            "host": target.host,
+            "port": target.port,
+            "address": address,
+            "sent": count,
+            "received": rtts.len(),
+            "loss_percent": lost * 100 / count,
+            "rtt_ms": samples,
+            "min_ms": min,
+            "avg_ms": avg,
+            "max_ms": max,
+        }));
+    }
 
-    if output.status.success() {
-        let stdout: std::borrow::Cow<'_, str> = String::from_utf8_lossy(&output.stdout);
-        println!("{}", stdout);
-    } else {
-        let stderr: std::borrow::Cow<'_, str> = String::from_utf8_lossy(&output.stderr);
-        eprintln!("{} Pingに失敗しました: {}", "!!".red(), stderr);
+    println!("\n--- {} ping 統計 ---", target.host);
+    println!("{} 回送信, {} 回受信, {}% 損失", count, rtts.len(), lost * 100 / count);
+    if let (Some(min), Some(avg), Some(max)) = (min, avg, max) {
+        println!("最小/平均/最大 = {:.1}/{:.1}/{:.1} ms", min, avg, max);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
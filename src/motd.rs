@@ -0,0 +1,574 @@
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use std::io::IsTerminal;
+
+const COLORS: [(char, &str, &str); 16] = [
+    ('0', "black", "#000000"),
+    ('1', "dark_blue", "#0000AA"),
+    ('2', "dark_green", "#00AA00"),
+    ('3', "dark_aqua", "#00AAAA"),
+    ('4', "dark_red", "#AA0000"),
+    ('5', "dark_purple", "#AA00AA"),
+    ('6', "gold", "#FFAA00"),
+    ('7', "gray", "#AAAAAA"),
+    ('8', "dark_gray", "#555555"),
+    ('9', "blue", "#5555FF"),
+    ('a', "green", "#55FF55"),
+    ('b', "aqua", "#55FFFF"),
+    ('c', "red", "#FF5555"),
+    ('d', "light_purple", "#FF55FF"),
+    ('e', "yellow", "#FFFF55"),
+    ('f', "white", "#FFFFFF"),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+pub enum MotdFormat {
+    Raw,
+    Clean,
+    Html,
+    Ansi,
+    Json,
+    Markdown,
+}
+
+const FORMATS: [(&str, char); 5] = [
+    ("obfuscated", 'k'),
+    ("bold", 'l'),
+    ("strikethrough", 'm'),
+    ("underlined", 'n'),
+    ("italic", 'o'),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    fn from_hex(hex: &str) -> Option<Self> {
+        let hex: &str = hex.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let v: u32 = u32::from_str_radix(hex, 16).ok()?;
+        Some(Rgb((v >> 16) as u8, (v >> 8) as u8, v as u8))
+    }
+
+    // 6x6x6 のカラーキューブ、または24段階のグレースケールに丸める
+    fn to_256(self) -> u8 {
+        let Rgb(r, g, b) = self;
+        if r == g && g == b {
+            return match r {
+                0..8 => 16,
+                249.. => 231,
+                _ => 232 + ((r as u16 - 8) * 24 / 241) as u8,
+            };
+        }
+        let q = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+        16 + 36 * q(r) + 6 * q(g) + q(b)
+    }
+}
+
+fn color_by_code(code: char) -> Option<Rgb> {
+    COLORS.iter().find(|(c, _, _)| *c == code).and_then(|(_, _, hex)| Rgb::from_hex(hex))
+}
+
+fn palette(rgb: Rgb) -> Option<(char, &'static str)> {
+    COLORS.iter().find(|(_, _, hex)| Rgb::from_hex(hex) == Some(rgb)).map(|(code, name, _)| (*code, *name))
+}
+
+fn color_by_name(name: &str) -> Option<Rgb> {
+    if name.starts_with('#') {
+        return Rgb::from_hex(name);
+    }
+    COLORS.iter().find(|(_, n, _)| *n == name).and_then(|(_, _, hex)| Rgb::from_hex(hex))
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Style {
+    pub color: Option<Rgb>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underlined: bool,
+    pub strikethrough: bool,
+    pub obfuscated: bool,
+}
+
+impl Style {
+    fn flags(&self) -> [(char, bool); 5] {
+        [
+            ('k', self.obfuscated),
+            ('l', self.bold),
+            ('m', self.strikethrough),
+            ('n', self.underlined),
+            ('o', self.italic),
+        ]
+    }
+
+    fn set_format(&mut self, code: char, on: bool) {
+        match code {
+            'k' => self.obfuscated = on,
+            'l' => self.bold = on,
+            'm' => self.strikethrough = on,
+            'n' => self.underlined = on,
+            'o' => self.italic = on,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+fn push_text(spans: &mut Vec<Span>, style: Style, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span { text: text.to_string(), style }),
+    }
+}
+
+fn push_legacy_spans(raw: &str, base: Style, spans: &mut Vec<Span>) {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut style: Style = base;
+    let mut text: String = String::new();
+    let mut i: usize = 0;
+    while i < chars.len() {
+        if chars[i] != '§' {
+            text.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let Some(code) = chars.get(i + 1).map(|c: &char| c.to_ascii_lowercase()) else {
+            break;
+        };
+        push_text(spans, style, &text);
+        text.clear();
+        i += 2;
+
+        // Spigot 形式の16進カラー: §x§R§R§G§G§B§B
+        if code == 'x' {
+            let digits: Option<String> = (0..6)
+                .map(|k: usize| if chars.get(i + k * 2) == Some(&'§') { chars.get(i + k * 2 + 1).copied() } else { None })
+                .collect();
+            if let Some(rgb) = digits.and_then(|d: String| Rgb::from_hex(&format!("#{}", d))) {
+                style = Style { color: Some(rgb), ..Style::default() };
+                i += 12;
+            }
+            continue;
+        }
+        // 色コードは書式もリセットする
+        if let Some(rgb) = color_by_code(code) {
+            style = Style { color: Some(rgb), ..Style::default() };
+        } else if code == 'r' {
+            style = base;
+        } else {
+            style.set_format(code, true);
+        }
+    }
+    push_text(spans, style, &text);
+}
+
+fn push_component(component: &Value, parent: Style, spans: &mut Vec<Span>) {
+    match component {
+        Value::String(s) => push_legacy_spans(s, parent, spans),
+        Value::Array(items) => {
+            for item in items {
+                push_component(item, parent, spans);
+            }
+        }
+        Value::Object(map) => {
+            let mut style: Style = parent;
+            if let Some(rgb) = map.get("color").and_then(Value::as_str).and_then(color_by_name) {
+                style.color = Some(rgb);
+            }
+            for (key, code) in FORMATS {
+                if let Some(on) = map.get(key).and_then(Value::as_bool) {
+                    style.set_format(code, on);
+                }
+            }
+            if let Some(text) = map.get("text").and_then(Value::as_str) {
+                push_legacy_spans(text, style, spans);
+            }
+            if let Some(extra) = map.get("extra") {
+                push_component(extra, style, spans);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn parse_legacy(raw: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    push_legacy_spans(raw, Style::default(), &mut spans);
+    spans
+}
+
+pub fn parse_component(component: &Value) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    push_component(component, Style::default(), &mut spans);
+    spans
+}
+
+fn push_style_codes(prev: Style, next: Style, out: &mut String) {
+    if prev == next {
+        return;
+    }
+    // 色が同じで書式が増えただけなら、増えた書式コードだけを追加する
+    let additive: bool = prev.color == next.color && prev.flags().iter().zip(next.flags()).all(|((_, p), (_, n))| !p || n);
+    if !additive {
+        match next.color.map(|rgb: Rgb| (rgb, palette(rgb))) {
+            Some((_, Some((code, _)))) => {
+                out.push('§');
+                out.push(code);
+            }
+            Some((rgb, None)) => {
+                out.push_str("§x");
+                for c in format!("{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2).chars() {
+                    out.push('§');
+                    out.push(c);
+                }
+            }
+            None => out.push_str("§r"),
+        }
+    }
+    for ((code, was), (_, on)) in prev.flags().into_iter().zip(next.flags()) {
+        if on && (!additive || !was) {
+            out.push('§');
+            out.push(code);
+        }
+    }
+}
+
+pub fn to_raw(spans: &[Span]) -> String {
+    let mut out: String = String::new();
+    let mut prev: Style = Style::default();
+    for span in spans {
+        push_style_codes(prev, span.style, &mut out);
+        out.push_str(&span.text);
+        prev = span.style;
+    }
+    out
+}
+
+pub fn to_legacy(component: &Value) -> String {
+    to_raw(&parse_component(component))
+}
+
+pub fn to_clean(spans: &[Span]) -> String {
+    spans.iter().map(|s: &Span| s.text.as_str()).collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn to_html(spans: &[Span]) -> String {
+    let mut out: String = String::new();
+    for span in spans {
+        let body: String = escape_html(&span.text).replace('\n', "<br>");
+        let mut style: String = String::new();
+        if let Some(rgb) = span.style.color {
+            style.push_str(&format!(" color: #{:02X}{:02X}{:02X};", rgb.0, rgb.1, rgb.2));
+        }
+        // text-decoration は後の宣言で上書きされるため、下線と取り消し線は1つにまとめる
+        let decoration: &str = match (span.style.underlined, span.style.strikethrough) {
+            (true, true) => "text-decoration: underline line-through;",
+            (true, false) => "text-decoration: underline;",
+            (false, true) => "text-decoration: line-through;",
+            (false, false) => "",
+        };
+        for (on, css) in [
+            (span.style.bold, "font-weight: bold;"),
+            (span.style.italic, "font-style: italic;"),
+            (!decoration.is_empty(), decoration),
+        ] {
+            if on {
+                style.push(' ');
+                style.push_str(css);
+            }
+        }
+        if style.is_empty() {
+            out.push_str(&body);
+        } else {
+            out.push_str(&format!("<span style=\"{}\">{}</span>", style.trim(), body));
+        }
+    }
+    out
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out: String = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_~[]<>#|".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Markdown では色を表現できないため、太字・斜体・取り消し線だけを残す
+pub fn to_markdown(spans: &[Span]) -> String {
+    let mut out: String = String::new();
+    for span in spans {
+        let mut marker: String = String::new();
+        for (on, m) in [(span.style.bold, "**"), (span.style.italic, "*"), (span.style.strikethrough, "~~")] {
+            if on {
+                marker.push_str(m);
+            }
+        }
+        let closing: String = marker.chars().rev().collect();
+        for (i, part) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                out.push_str("  \n");
+            }
+            let body: &str = part.trim();
+            if marker.is_empty() || body.is_empty() {
+                out.push_str(&escape_markdown(part));
+                continue;
+            }
+            // 記号の内側に空白があると強調として解釈されないため外に出す
+            let lead: &str = &part[..part.len() - part.trim_start().len()];
+            let trail: &str = &part[part.trim_end().len()..];
+            out.push_str(&format!("{}{}{}{}{}", lead, marker, escape_markdown(body), closing, trail));
+        }
+    }
+    out
+}
+
+pub fn to_json(spans: &[Span]) -> Value {
+    let extra: Vec<Value> = spans
+        .iter()
+        .map(|span: &Span| {
+            let mut obj: Map<String, Value> = Map::new();
+            obj.insert("text".into(), Value::String(span.text.clone()));
+            if let Some(rgb) = span.style.color {
+                let name: String = match palette(rgb) {
+                    Some((_, name)) => name.to_string(),
+                    None => format!("#{:02X}{:02X}{:02X}", rgb.0, rgb.1, rgb.2),
+                };
+                obj.insert("color".into(), Value::String(name));
+            }
+            for ((key, _), (_, on)) in FORMATS.iter().zip(span.style.flags()) {
+                if on {
+                    obj.insert(key.to_string(), Value::Bool(true));
+                }
+            }
+            Value::Object(obj)
+        })
+        .collect();
+    json!({ "text": "", "extra": extra })
+}
+
+pub fn render(spans: &[Span], format: MotdFormat) -> String {
+    match format {
+        MotdFormat::Raw => to_raw(spans),
+        MotdFormat::Clean => to_clean(spans),
+        MotdFormat::Html => to_html(spans),
+        MotdFormat::Ansi => to_ansi(spans, detect_color_mode()),
+        MotdFormat::Json => serde_json::to_string_pretty(&to_json(spans)).unwrap_or_default(),
+        MotdFormat::Markdown => to_markdown(spans),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorMode {
+    TrueColor,
+    Ansi256,
+    Plain,
+}
+
+pub fn detect_color_mode() -> ColorMode {
+    let dumb: bool = std::env::var("TERM").is_ok_and(|t: String| t == "dumb");
+    if std::env::var_os("NO_COLOR").is_some() || dumb || !std::io::stdout().is_terminal() {
+        return ColorMode::Plain;
+    }
+    match std::env::var("COLORTERM").as_deref() {
+        Ok("truecolor") | Ok("24bit") => ColorMode::TrueColor,
+        _ => ColorMode::Ansi256,
+    }
+}
+
+// クライアントと同じく、難読化された文字は表示のたびにランダムな文字に置き換える
+fn obfuscate(text: &str) -> String {
+    const GLYPHS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789#$%&?@";
+    let mut seed: u32 = chrono::Utc::now().timestamp_subsec_nanos() | 1;
+    text.chars()
+        .map(|c: char| {
+            if c.is_whitespace() {
+                return c;
+            }
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            GLYPHS[seed as usize % GLYPHS.len()] as char
+        })
+        .collect()
+}
+
+pub fn to_ansi(spans: &[Span], mode: ColorMode) -> String {
+    let mut out: String = String::new();
+    for span in spans {
+        let text: String = if span.style.obfuscated { obfuscate(&span.text) } else { span.text.clone() };
+        if mode == ColorMode::Plain {
+            out.push_str(&text);
+            continue;
+        }
+        let mut codes: Vec<String> = Vec::new();
+        if let Some(rgb) = span.style.color {
+            codes.push(match mode {
+                ColorMode::TrueColor => format!("38;2;{};{};{}", rgb.0, rgb.1, rgb.2),
+                _ => format!("38;5;{}", rgb.to_256()),
+            });
+        }
+        for (on, code) in [(span.style.bold, "1"), (span.style.italic, "3"), (span.style.underlined, "4"), (span.style.strikethrough, "9")] {
+            if on {
+                codes.push(code.to_string());
+            }
+        }
+        if codes.is_empty() {
+            out.push_str(&text);
+        } else {
+            out.push_str(&format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text));
+        }
+    }
+    out
+}
+
+pub fn to_lines(spans: &[Span]) -> Vec<ratatui::text::Line<'static>> {
+    use ratatui::style::{Color, Modifier};
+
+    let mut lines: Vec<ratatui::text::Line<'static>> = vec![ratatui::text::Line::default()];
+    for span in spans {
+        let mut style: ratatui::style::Style = ratatui::style::Style::default();
+        if let Some(rgb) = span.style.color {
+            style = style.fg(Color::Rgb(rgb.0, rgb.1, rgb.2));
+        }
+        for (on, modifier) in [
+            (span.style.bold, Modifier::BOLD),
+            (span.style.italic, Modifier::ITALIC),
+            (span.style.underlined, Modifier::UNDERLINED),
+            (span.style.strikethrough, Modifier::CROSSED_OUT),
+        ] {
+            if on {
+                style = style.add_modifier(modifier);
+            }
+        }
+        let text: String = if span.style.obfuscated { obfuscate(&span.text) } else { span.text.clone() };
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(ratatui::text::Line::default());
+            }
+            if let Some(line) = lines.last_mut() {
+                line.push_span(ratatui::text::Span::styled(part.to_string(), style));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_legacy_codes() {
+        let spans: Vec<Span> = parse_legacy("§aHaven§lMC §rnow §x§f§f§0§0§0§0red");
+        let texts: Vec<&str> = spans.iter().map(|s: &Span| s.text.as_str()).collect();
+        assert_eq!(texts, ["Haven", "MC ", "now ", "red"]);
+        assert_eq!(spans[0].style.color, Some(Rgb(0x55, 0xFF, 0x55)));
+        assert!(spans[1].style.bold && spans[1].style.color == spans[0].style.color);
+        assert_eq!(spans[2].style, Style::default());
+        assert_eq!(spans[3].style.color, Some(Rgb(0xFF, 0, 0)));
+    }
+
+    #[test]
+    fn parses_json_components() {
+        let spans: Vec<Span> = parse_component(&json!({
+            "text": "Haven",
+            "color": "#123456",
+            "bold": true,
+            "extra": [{ "text": "MC", "italic": true, "bold": false }, "§ckun"],
+        }));
+        assert_eq!(spans.len(), 3);
+        assert_eq!((spans[0].style.color, spans[0].style.bold), (Some(Rgb(0x12, 0x34, 0x56)), true));
+        assert_eq!((spans[1].style.bold, spans[1].style.italic), (false, true));
+        assert_eq!(spans[2].style.color, Some(Rgb(0xFF, 0x55, 0x55)));
+    }
+
+    fn sample() -> Vec<Span> {
+        parse_component(&json!({
+            "text": "Haven",
+            "color": "gold",
+            "extra": [
+                { "text": "MC", "bold": true },
+                { "text": " <Survival>\n", "color": "#123456" },
+                { "text": "open now", "italic": true, "strikethrough": true },
+            ],
+        }))
+    }
+
+    #[test]
+    fn renders_raw() {
+        let raw: String = to_raw(&sample());
+        assert_eq!(raw, "§6Haven§lMC§x§1§2§3§4§5§6 <Survival>\n§6§m§oopen now");
+        assert_eq!(parse_legacy(&raw), sample());
+        assert_eq!(to_raw(&parse_legacy("a§cb§rc")), "a§cb§rc");
+    }
+
+    #[test]
+    fn renders_clean() {
+        assert_eq!(to_clean(&sample()), "HavenMC <Survival>\nopen now");
+    }
+
+    #[test]
+    fn renders_html() {
+        assert_eq!(
+            to_html(&sample()),
+            "<span style=\"color: #FFAA00;\">Haven</span>\
+             <span style=\"color: #FFAA00; font-weight: bold;\">MC</span>\
+             <span style=\"color: #123456;\"> &lt;Survival&gt;<br></span>\
+             <span style=\"color: #FFAA00; font-style: italic; text-decoration: line-through;\">open now</span>"
+        );
+        assert_eq!(
+            to_html(&parse_legacy("§n§mboth§r plain")),
+            "<span style=\"text-decoration: underline line-through;\">both</span> plain"
+        );
+    }
+
+    #[test]
+    fn renders_json() {
+        assert_eq!(
+            to_json(&sample()),
+            json!({ "text": "", "extra": [
+                { "text": "Haven", "color": "gold" },
+                { "text": "MC", "color": "gold", "bold": true },
+                { "text": " <Survival>\n", "color": "#123456" },
+                { "text": "open now", "color": "gold", "strikethrough": true, "italic": true },
+            ]})
+        );
+        assert_eq!(parse_component(&to_json(&sample())), sample());
+    }
+
+    #[test]
+    fn renders_markdown() {
+        assert_eq!(to_markdown(&sample()), "Haven**MC** \\<Survival\\>  \n*~~open now~~*");
+        assert_eq!(to_markdown(&parse_legacy("§l bold ")), " **bold** ");
+    }
+
+    #[test]
+    fn renders_ansi() {
+        let spans: Vec<Span> = parse_legacy("§6§lGold");
+        assert_eq!(to_ansi(&spans, ColorMode::TrueColor), "\x1b[38;2;255;170;0;1mGold\x1b[0m");
+        assert_eq!(to_ansi(&spans, ColorMode::Ansi256), "\x1b[38;5;214;1mGold\x1b[0m");
+        assert_eq!(to_ansi(&spans, ColorMode::Plain), "Gold");
+    }
+}
@@ -0,0 +1,210 @@
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Tsv,
+    // --quiet 指定時に使う。何も出力しない
+    #[value(skip)]
+    Quiet,
+}
+
+impl OutputFormat {
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Text
+    }
+}
+
+pub fn document(command: &str, server: &str, data: Value) -> Value {
+    let mut doc: Map<String, Value> = Map::new();
+    doc.insert("schema".into(), Value::String(format!("haole.{}/v{}", command, SCHEMA_VERSION)));
+    doc.insert("server".into(), Value::String(server.to_string()));
+    if let Value::Object(fields) = data {
+        doc.extend(fields);
+    }
+    Value::Object(doc)
+}
+
+pub fn render(format: OutputFormat, doc: &Value) -> Result<String, Box<dyn std::error::Error>> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(doc)?),
+        OutputFormat::Yaml => {
+            let mut out: String = String::from("---\n");
+            write_yaml(doc, 0, &mut out);
+            Ok(out.trim_end().to_string())
+        }
+        OutputFormat::Tsv => {
+            let mut rows: Vec<(String, String)> = Vec::new();
+            flatten("", doc, &mut rows);
+            Ok(rows.iter().map(|(k, v)| format!("{}\t{}", k, v)).collect::<Vec<String>>().join("\n"))
+        }
+        OutputFormat::Quiet => Ok(String::new()),
+        OutputFormat::Text => Err("テキスト出力は各コマンドで行います。".into()),
+    }
+}
+
+pub fn emit(format: OutputFormat, command: &str, server: &str, data: Value) -> Result<(), Box<dyn std::error::Error>> {
+    if format != OutputFormat::Quiet {
+        println!("{}", render(format, &document(command, server, data))?);
+    }
+    Ok(())
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        // JSON の文字列表記は YAML のダブルクォート文字列としてそのまま使える
+        Value::String(s) => serde_json::to_string(s).unwrap_or_default(),
+        Value::Array(a) if a.is_empty() => "[]".into(),
+        Value::Object(o) if o.is_empty() => "{}".into(),
+        _ => String::new(),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    match value {
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        _ => true,
+    }
+}
+
+fn write_yaml(value: &Value, indent: usize, out: &mut String) {
+    let pad: String = "  ".repeat(indent);
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                if is_scalar(v) {
+                    out.push_str(&format!("{}{}: {}\n", pad, key, yaml_scalar(v)));
+                } else {
+                    out.push_str(&format!("{}{}:\n", pad, key));
+                    write_yaml(v, indent + 1, out);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                if is_scalar(item) {
+                    out.push_str(&format!("{}- {}\n", pad, yaml_scalar(item)));
+                } else {
+                    out.push_str(&format!("{}-\n", pad));
+                    write_yaml(item, indent + 1, out);
+                }
+            }
+        }
+        _ => out.push_str(&format!("{}{}\n", pad, yaml_scalar(value))),
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, rows: &mut Vec<(String, String)>) {
+    let join = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, v) in map {
+                flatten(&join(key), v, rows);
+            }
+        }
+        Value::Object(_) => rows.push((prefix.to_string(), String::new())),
+        Value::Array(items) if items.is_empty() => rows.push((prefix.to_string(), String::new())),
+        // スカラーの配列は同じキーで1要素1行にする
+        Value::Array(items) if items.iter().all(is_scalar) => {
+            for item in items {
+                flatten(prefix, item, rows);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(&join(&i.to_string()), item, rows);
+            }
+        }
+        Value::Null => rows.push((prefix.to_string(), String::new())),
+        Value::String(s) => rows.push((prefix.to_string(), s.replace('\t', " ").replace('\n', "\\n"))),
+        other => rows.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        document("test", "HavenMC", json!({
+            "nested": { "online": true, "players": { "online": 2, "max": 20 } },
+            "events": [{ "event": "join", "player": "Steve" }, { "event": "leave", "player": "Alex" }],
+            "names": ["Steve", "Alex"],
+            "missing": null,
+            "empty_list": [],
+            "empty_map": {},
+            "tricky": ["a: b", "# comment", "- item", "line1\nline2", "tab\there"],
+        }))
+    }
+
+    #[test]
+    fn renders_yaml() {
+        let yaml: String = render(OutputFormat::Yaml, &sample()).unwrap();
+        assert_eq!(yaml, [
+            "---",
+            "schema: \"haole.test/v1\"",
+            "server: \"HavenMC\"",
+            "nested:",
+            "  online: true",
+            "  players:",
+            "    online: 2",
+            "    max: 20",
+            "events:",
+            "  -",
+            "    event: \"join\"",
+            "    player: \"Steve\"",
+            "  -",
+            "    event: \"leave\"",
+            "    player: \"Alex\"",
+            "names:",
+            "  - \"Steve\"",
+            "  - \"Alex\"",
+            "missing: null",
+            "empty_list: []",
+            "empty_map: {}",
+            "tricky:",
+            "  - \"a: b\"",
+            "  - \"# comment\"",
+            "  - \"- item\"",
+            "  - \"line1\\nline2\"",
+            "  - \"tab\\there\"",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn renders_tsv() {
+        let tsv: String = render(OutputFormat::Tsv, &sample()).unwrap();
+        assert_eq!(tsv, [
+            "schema\thaole.test/v1",
+            "server\tHavenMC",
+            "nested.online\ttrue",
+            "nested.players.online\t2",
+            "nested.players.max\t20",
+            "events.0.event\tjoin",
+            "events.0.player\tSteve",
+            "events.1.event\tleave",
+            "events.1.player\tAlex",
+            "names\tSteve",
+            "names\tAlex",
+            "missing\t",
+            "empty_list\t",
+            "empty_map\t",
+            "tricky\ta: b",
+            "tricky\t# comment",
+            "tricky\t- item",
+            "tricky\tline1\\nline2",
+            "tricky\ttab here",
+        ].join("\n"));
+    }
+}
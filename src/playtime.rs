@@ -0,0 +1,126 @@
+use crate::history::Sample;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+
+// 取得の間隔がこれより空いたら、その間のプレイ状況は分からないものとしてセッションを区切る
+const MAX_GAP_MINUTES: i64 = 10;
+
+pub struct PlayerRecord {
+    pub first_seen: DateTime<Local>,
+    pub last_seen: DateTime<Local>,
+    pub total: chrono::Duration,
+    pub sessions: u32,
+    // オンライン中なら今のセッションで最初に見えた時刻
+    pub current: Option<DateTime<Local>>,
+}
+
+impl PlayerRecord {
+    pub fn session_length(&self, now: DateTime<Local>) -> Option<chrono::Duration> {
+        self.current.map(|start: DateTime<Local>| now - start)
+    }
+}
+
+// 履歴のサンプルを順に与えて、プレイヤーごとの記録を積み上げる
+#[derive(Default)]
+pub struct Playtime {
+    records: HashMap<String, PlayerRecord>,
+    last: Option<DateTime<Local>>,
+}
+
+impl Playtime {
+    pub fn from_history(samples: &[Sample]) -> Self {
+        let mut playtime: Self = Self::default();
+        for sample in samples {
+            playtime.update(sample);
+        }
+        playtime
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerRecord> {
+        self.records.get(name)
+    }
+
+    pub fn update(&mut self, sample: &Sample) {
+        let gap: Option<chrono::Duration> = self.last.map(|last: DateTime<Local>| sample.time - last);
+        let continuous: bool = gap.is_some_and(|g: chrono::Duration| g <= chrono::Duration::minutes(MAX_GAP_MINUTES));
+        let (players, complete): (&[String], bool) = match sample.known_players() {
+            Some(players) => (players, true),
+            None if !sample.online => (&[], true),
+            // 一覧が分からないときは何も変えない
+            None if sample.players.is_empty() => return,
+            // 一部の名前しか返ってこないときは、見えたプレイヤーだけ更新し、見えないプレイヤーのセッションは続いているものとする
+            None => (&sample.players, false),
+        };
+        self.last = Some(sample.time);
+
+        for (name, record) in self.records.iter_mut() {
+            if (complete && !players.contains(name)) || !continuous {
+                record.current = None;
+            }
+        }
+        for name in players {
+            let record: &mut PlayerRecord = self.records.entry(name.clone()).or_insert(PlayerRecord {
+                first_seen: sample.time,
+                last_seen: sample.time,
+                total: chrono::Duration::zero(),
+                sessions: 0,
+                current: None,
+            });
+            match record.current {
+                Some(_) => record.total += sample.time - record.last_seen,
+                None => {
+                    record.sessions += 1;
+                    record.current = Some(sample.time);
+                }
+            }
+            record.last_seen = sample.time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minute: u32, players: &[&str]) -> Sample {
+        Sample::at(1, 12, minute).with_players(players)
+    }
+
+    #[test]
+    fn keeps_sessions_across_truncated_samples() {
+        let playtime: Playtime = Playtime::from_history(&[
+            sample(0, &["Steve", "Alex"]),
+            // 20人のうち一部の名前だけが返ってきた
+            sample(5, &["Alex"]).with_count(20),
+            sample(8, &["Steve"]).with_count(20),
+            sample(12, &["Steve"]),
+        ]);
+
+        let steve: &PlayerRecord = playtime.get("Steve").unwrap();
+        assert_eq!((steve.sessions, steve.total.num_minutes()), (1, 12));
+        let alex: &PlayerRecord = playtime.get("Alex").unwrap();
+        assert_eq!((alex.sessions, alex.total.num_minutes(), alex.current), (1, 5, None));
+    }
+
+    #[test]
+    fn accumulates_sessions_and_playtime() {
+        let playtime: Playtime = Playtime::from_history(&[
+            sample(0, &["Steve"]),
+            sample(5, &["Steve", "Alex"]),
+            sample(8, &["Alex"]),
+            sample(9, &["Alex", "Steve"]),
+            // 間隔が空いたので Alex のセッションは区切られる
+            sample(30, &["Alex", "Steve"]),
+        ]);
+
+        let steve: &PlayerRecord = playtime.get("Steve").unwrap();
+        assert_eq!((steve.sessions, steve.total.num_minutes()), (3, 5));
+        assert_eq!(steve.first_seen, sample(0, &[]).time);
+        assert_eq!(steve.last_seen, sample(30, &[]).time);
+
+        let alex: &PlayerRecord = playtime.get("Alex").unwrap();
+        assert_eq!((alex.sessions, alex.total.num_minutes()), (2, 4));
+        assert_eq!(alex.current, Some(sample(30, &[]).time));
+        assert_eq!(alex.session_length(sample(40, &[]).time).map(|d: chrono::Duration| d.num_minutes()), Some(10));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::config::ServerProfile;
+use crate::{HavenStatus, McStatusIOResponse};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use tokio::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    HavenApi,
+    Slp,
+    Mcstatus,
+}
+
+impl Source {
+    // 履歴やメトリクスに記録する名前
+    pub fn key(self) -> &'static str {
+        match self {
+            Source::HavenApi => "haven_api",
+            Source::Slp => "slp",
+            Source::Mcstatus => "mcstatus",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::HavenApi => "HavenMC API",
+            Source::Slp => "サーバーへの直接問い合わせ",
+            Source::Mcstatus => "mcstatus.io",
+        }
+    }
+
+    fn provider(self) -> Box<dyn StatusProvider> {
+        match self {
+            Source::HavenApi => Box::new(HavenApi),
+            Source::Slp => Box::new(Direct),
+            Source::Mcstatus => Box::new(McStatusIo),
+        }
+    }
+}
+
+pub const DEFAULT_ORDER: [Source; 3] = [Source::HavenApi, Source::Slp, Source::Mcstatus];
+
+// 直接問い合わせ・mcstatus.io の元の応答。MOTD やアイコンなど、ステータス以外の表示に使い回す
+pub enum Details {
+    Slp(crate::slp::SlpResponse),
+    Mcstatus(McStatusIOResponse),
+}
+
+// どの取得元が答えたかを添えたステータス
+pub struct Answer {
+    pub source: Source,
+    pub status: HavenStatus,
+    pub latency: Option<Duration>,
+    pub details: Option<Details>,
+}
+
+type AnswerFuture<'a> = Pin<Box<dyn Future<Output = Result<Answer, Box<dyn std::error::Error>>> + 'a>>;
+
+pub trait StatusProvider {
+    fn source(&self) -> Source;
+
+    // このサーバーに対して使えない取得元は、失敗扱いにせず飛ばす
+    fn supports(&self, _target: &ServerProfile) -> bool {
+        true
+    }
+
+    fn fetch<'a>(&'a self, target: &'a ServerProfile) -> AnswerFuture<'a>;
+}
+
+struct HavenApi;
+
+impl StatusProvider for HavenApi {
+    fn source(&self) -> Source {
+        Source::HavenApi
+    }
+
+    fn supports(&self, target: &ServerProfile) -> bool {
+        target.api_url.is_some()
+    }
+
+    fn fetch<'a>(&'a self, target: &'a ServerProfile) -> AnswerFuture<'a> {
+        Box::pin(async move {
+            let status: HavenStatus = crate::fetch_haven_status(target).await?;
+            Ok(Answer { source: self.source(), status, latency: None, details: None })
+        })
+    }
+}
+
+struct Direct;
+
+impl StatusProvider for Direct {
+    fn source(&self) -> Source {
+        Source::Slp
+    }
+
+    fn fetch<'a>(&'a self, target: &'a ServerProfile) -> AnswerFuture<'a> {
+        Box::pin(async move {
+            let resp: crate::slp::SlpResponse = crate::query_server(&target.host, target.port).await?;
+            Ok(Answer { source: self.source(), status: HavenStatus::from_slp(&resp), latency: Some(resp.latency), details: Some(Details::Slp(resp)) })
+        })
+    }
+}
+
+struct McStatusIo;
+
+impl StatusProvider for McStatusIo {
+    fn source(&self) -> Source {
+        Source::Mcstatus
+    }
+
+    fn fetch<'a>(&'a self, target: &'a ServerProfile) -> AnswerFuture<'a> {
+        Box::pin(async move {
+            let resp: McStatusIOResponse = crate::fetch_haven_status_by_mcstatusio(target).await?;
+            let status: HavenStatus = HavenStatus::from_mcstatusio(&resp);
+            Ok(Answer { source: self.source(), status, latency: None, details: Some(Details::Mcstatus(resp)) })
+        })
+    }
+}
+
+// すべての取得元が失敗したときのエラー
+#[derive(Debug)]
+pub struct Failure {
+    // 試した順の取得元と、それぞれのエラー (空にはならない)
+    pub attempts: Vec<(Source, Box<dyn std::error::Error>)>,
+}
+
+impl Failure {
+    // 優先度がいちばん高い取得元の失敗を、主な原因とみなす
+    pub fn primary(&self) -> (Source, &(dyn std::error::Error + 'static)) {
+        let (source, err) = &self.attempts[0];
+        (*source, err.as_ref())
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let messages: Vec<String> = self.attempts.iter().map(|(source, err)| format!("{}: {}", source.label(), err)).collect();
+        f.write_str(&messages.join(" / "))
+    }
+}
+
+impl std::error::Error for Failure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.primary().1)
+    }
+}
+
+// 設定された順に取得元を試し、最初に答えた結果を返す
+pub async fn fetch(target: &ServerProfile) -> Result<Answer, Box<dyn std::error::Error>> {
+    let order: &[Source] = if target.providers.is_empty() { &DEFAULT_ORDER } else { &target.providers };
+    fetch_from(target, order.iter().map(|s: &Source| s.provider()).collect()).await
+}
+
+// MOTD やアドレスなど、ステータス以外の情報を返せる取得元 (HavenMC API 以外) だけを設定された順に試す
+pub async fn fetch_details(target: &ServerProfile) -> Result<(Source, Details), Box<dyn std::error::Error>> {
+    let order: &[Source] = if target.providers.is_empty() { &DEFAULT_ORDER } else { &target.providers };
+    let providers: Vec<Box<dyn StatusProvider>> = order.iter().filter(|s: &&Source| **s != Source::HavenApi).map(|s: &Source| s.provider()).collect();
+    let answer: Answer = fetch_from(target, providers).await?;
+    match answer.details {
+        Some(details) => Ok((answer.source, details)),
+        None => Err(crate::error::HaoleError::parse(format!("{} が詳細情報を返しませんでした", answer.source.label()))),
+    }
+}
+
+async fn fetch_from(target: &ServerProfile, providers: Vec<Box<dyn StatusProvider>>) -> Result<Answer, Box<dyn std::error::Error>> {
+    let mut attempts: Vec<(Source, Box<dyn std::error::Error>)> = Vec::new();
+    for provider in providers {
+        if !provider.supports(target) {
+            continue;
+        }
+        match provider.fetch(target).await {
+            Ok(answer) => return Ok(answer),
+            Err(e) => attempts.push((provider.source(), e)),
+        }
+    }
+    if attempts.is_empty() {
+        return Err(crate::error::HaoleError::config("使用できる取得元がありません。providers の設定を確認してください。"));
+    }
+    Err(Box::new(Failure { attempts }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Players;
+
+    // 決まった結果を返す取得元
+    struct Fake(Source, bool);
+
+    impl StatusProvider for Fake {
+        fn source(&self) -> Source {
+            self.0
+        }
+
+        fn fetch<'a>(&'a self, _target: &'a ServerProfile) -> AnswerFuture<'a> {
+            Box::pin(async move {
+                if !self.1 {
+                    return Err(format!("{} に接続できません", self.0.key()).into());
+                }
+                let status: HavenStatus = HavenStatus { online: true, players: Players { online: 1, max: 20, list: None }, version: "1.21.4".into() };
+                Ok(Answer { source: self.0, status, latency: None, details: None })
+            })
+        }
+    }
+
+    fn profile(api_url: Option<&str>) -> ServerProfile {
+        let mut profile: ServerProfile = ServerProfile::havenmc();
+        profile.api_url = api_url.map(|u: &str| u.to_string());
+        profile
+    }
+
+    #[tokio::test]
+    async fn falls_back_in_order() {
+        let answer: Answer = fetch_from(&profile(None), vec![
+            Box::new(Fake(Source::HavenApi, false)),
+            Box::new(Fake(Source::Slp, true)),
+            Box::new(Fake(Source::Mcstatus, true)),
+        ]).await.unwrap();
+        assert_eq!(answer.source, Source::Slp);
+
+        let err: Box<dyn std::error::Error> = fetch_from(&profile(None), vec![
+            Box::new(Fake(Source::Mcstatus, false)),
+            Box::new(Fake(Source::Slp, false)),
+        ]).await.err().unwrap();
+        let failure: &Failure = err.downcast_ref::<Failure>().unwrap();
+        assert_eq!(failure.attempts.iter().map(|(s, _)| *s).collect::<Vec<Source>>(), [Source::Mcstatus, Source::Slp]);
+        assert_eq!(failure.primary().0, Source::Mcstatus);
+        assert_eq!(failure.primary().1.to_string(), "mcstatus に接続できません");
+    }
+
+    #[tokio::test]
+    async fn skips_haven_api_without_api_url() {
+        assert!(!HavenApi.supports(&profile(None)));
+        assert!(HavenApi.supports(&profile(Some("http://127.0.0.1:1/status"))));
+
+        // api_url がなければ HavenMC API には問い合わせない
+        let answer: Answer = fetch_from(&profile(None), vec![Box::new(HavenApi), Box::new(Fake(Source::Mcstatus, true))]).await.unwrap();
+        assert_eq!(answer.source, Source::Mcstatus);
+
+        let err: Box<dyn std::error::Error> = fetch_from(&profile(None), vec![Box::new(HavenApi)]).await.err().unwrap();
+        assert!(matches!(err.downcast_ref::<crate::error::HaoleError>(), Some(crate::error::HaoleError::Config(_))));
+        assert_eq!(crate::error::exit_code(err.as_ref()), crate::error::EXIT_CONFIG);
+    }
+
+    #[tokio::test]
+    async fn details_never_come_from_haven_api() {
+        // HavenMC API しか設定されていなければ、詳細を返せる取得元がない
+        let mut target: ServerProfile = profile(Some("http://127.0.0.1:1/status"));
+        target.providers = vec![Source::HavenApi];
+        let err: Box<dyn std::error::Error> = fetch_details(&target).await.err().unwrap();
+        assert_eq!(crate::error::exit_code(err.as_ref()), crate::error::EXIT_CONFIG);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::HaoleError;
+use std::collections::HashMap;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration};
+
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+const TYPE_HANDSHAKE: u8 = 0x09;
+const TYPE_STAT: u8 = 0x00;
+const IO_TIMEOUT: Duration = Duration::from_secs(3);
+// "splitnum\0\x80\0" の後にK/Vセクションが始まる
+const KV_PADDING: usize = 11;
+// "\x01player_\0\0" の後にプレイヤー名が並ぶ
+const PLAYER_PADDING: &[u8] = b"\x01player_\0\0";
+
+#[derive(Debug)]
+pub struct QueryStatus {
+    pub motd: String,
+    pub game_type: String,
+    pub version: String,
+    pub software: String,
+    pub plugins: Vec<String>,
+    pub map: String,
+    pub online: u32,
+    pub max: u32,
+    pub host_ip: String,
+    pub host_port: u16,
+    pub players: Vec<String>,
+}
+
+fn packet(kind: u8, session_id: i32, payload: &[u8]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(7 + payload.len());
+    buf.extend_from_slice(&MAGIC);
+    buf.push(kind);
+    buf.extend_from_slice(&session_id.to_be_bytes());
+    buf.extend_from_slice(payload);
+    buf
+}
+
+fn check_header(resp: &[u8], kind: u8, session_id: i32) -> Result<&[u8], Box<dyn std::error::Error>> {
+    if resp.len() < 5 || resp[0] != kind || resp[1..5] != session_id.to_be_bytes() {
+        return Err(HaoleError::parse("Query の応答ヘッダーが一致しません。"));
+    }
+    Ok(&resp[5..])
+}
+
+fn split_cstrings(buf: &[u8]) -> Vec<String> {
+    buf.split(|b: &u8| *b == 0)
+        .map(|s: &[u8]| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+fn parse_plugins(raw: &str) -> (String, Vec<String>) {
+    match raw.split_once(": ") {
+        Some((software, list)) => (
+            software.to_string(),
+            list.split("; ").filter(|p: &&str| !p.is_empty()).map(|p: &str| p.to_string()).collect(),
+        ),
+        None => (raw.to_string(), Vec::new()),
+    }
+}
+
+pub fn parse_full_stat(body: &[u8]) -> Result<QueryStatus, Box<dyn std::error::Error>> {
+    let body: &[u8] = body.get(KV_PADDING..).ok_or_else(|| HaoleError::parse("Query の応答が短すぎます。"))?;
+    let split_at: usize = body
+        .windows(PLAYER_PADDING.len())
+        .position(|w: &[u8]| w == PLAYER_PADDING)
+        .ok_or_else(|| HaoleError::parse("Query の応答にプレイヤーセクションがありません。"))?;
+
+    let kv: Vec<String> = split_cstrings(&body[..split_at]);
+    let values: HashMap<&str, &str> = kv
+        .chunks_exact(2)
+        .map(|pair: &[String]| (pair[0].as_str(), pair[1].as_str()))
+        .collect();
+    let get = |key: &str| values.get(key).copied().unwrap_or_default().to_string();
+
+    let players: Vec<String> = split_cstrings(&body[split_at + PLAYER_PADDING.len()..])
+        .into_iter()
+        .filter(|p: &String| !p.is_empty())
+        .collect();
+    let (software, plugins) = parse_plugins(&get("plugins"));
+
+    Ok(QueryStatus {
+        motd: get("hostname"),
+        game_type: get("gametype"),
+        version: get("version"),
+        software,
+        plugins,
+        map: get("map"),
+        online: get("numplayers").parse()?,
+        max: get("maxplayers").parse()?,
+        host_ip: get("hostip"),
+        host_port: get("hostport").parse().unwrap_or_default(),
+        players,
+    })
+}
+
+async fn exchange(socket: &UdpSocket, request: &[u8], buf: &mut [u8]) -> Result<usize, Box<dyn std::error::Error>> {
+    socket.send(request).await?;
+    let received: usize = timeout(IO_TIMEOUT, socket.recv(buf)).await.map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "Query の応答がありません。server.properties で enable-query=true になっているか確認してください。",
+        )
+    })??;
+    Ok(received)
+}
+
+pub async fn full_stat(host: &str, port: u16) -> Result<QueryStatus, Box<dyn std::error::Error>> {
+    let socket: UdpSocket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect((host, port)).await?;
+    let session_id: i32 = (chrono::Utc::now().timestamp_subsec_nanos() as i32) & 0x0F0F_0F0F;
+    let mut buf: Vec<u8> = vec![0; 65536];
+
+    let n: usize = exchange(&socket, &packet(TYPE_HANDSHAKE, session_id, &[]), &mut buf).await?;
+    let token_text: String = split_cstrings(check_header(&buf[..n], TYPE_HANDSHAKE, session_id)?)
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let token: i32 = token_text.trim().parse()?;
+
+    let mut payload: Vec<u8> = token.to_be_bytes().to_vec();
+    payload.extend_from_slice(&[0, 0, 0, 0]);
+    let n: usize = exchange(&socket, &packet(TYPE_STAT, session_id, &payload), &mut buf).await?;
+    parse_full_stat(check_header(&buf[..n], TYPE_STAT, session_id)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_stat_response() {
+        let mut body: Vec<u8> = b"splitnum\0\x80\0".to_vec();
+        for (k, v) in [
+            ("hostname", "HavenMC"),
+            ("gametype", "SMP"),
+            ("game_id", "MINECRAFT"),
+            ("version", "1.21.4"),
+            ("plugins", "Paper on 1.21.4: LuckPerms 5.4; EssentialsX 2.20"),
+            ("map", "world"),
+            ("numplayers", "2"),
+            ("maxplayers", "100"),
+            ("hostport", "25565"),
+            ("hostip", "127.0.0.1"),
+        ] {
+            body.extend_from_slice(k.as_bytes());
+            body.push(0);
+            body.extend_from_slice(v.as_bytes());
+            body.push(0);
+        }
+        body.push(0);
+        body.extend_from_slice(PLAYER_PADDING);
+        body.extend_from_slice(b"Steve\0Alex\0\0");
+
+        let st: QueryStatus = parse_full_stat(&body).unwrap();
+        assert_eq!((st.motd.as_str(), st.map.as_str()), ("HavenMC", "world"));
+        assert_eq!((st.online, st.max, st.host_port), (2, 100, 25565));
+        assert_eq!(st.software, "Paper on 1.21.4");
+        assert_eq!(st.plugins, ["LuckPerms 5.4", "EssentialsX 2.20"]);
+        assert_eq!(st.players, ["Steve", "Alex"]);
+    }
+
+    #[test]
+    fn plugins_without_list() {
+        assert_eq!(parse_plugins("Vanilla"), ("Vanilla".to_string(), Vec::new()));
+    }
+}
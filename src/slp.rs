@@ -0,0 +1,285 @@
+use crate::error::HaoleError;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+pub const DEFAULT_PORT: u16 = 25565;
+
+// ステータス取得時はプロトコルバージョンを問わないため -1 を送る
+const HANDSHAKE_PROTOCOL: i32 = -1;
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_PACKET_LEN: usize = 1 << 21;
+
+#[derive(Deserialize, Debug)]
+pub struct SlpStatus {
+    pub version: SlpVersion,
+    #[serde(default)]
+    pub players: Option<SlpPlayers>,
+    #[serde(default)]
+    pub description: serde_json::Value,
+    #[serde(default)]
+    pub favicon: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlpVersion {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlpPlayers {
+    pub max: u32,
+    pub online: u32,
+    #[serde(default)]
+    pub sample: Option<Vec<SlpSample>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SlpSample {
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct SlpResponse {
+    pub status: SlpStatus,
+    pub latency: Duration,
+    pub address: SocketAddr,
+}
+
+pub fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut v: u32 = value as u32;
+    loop {
+        if v & !0x7F == 0 {
+            buf.push(v as u8);
+            return;
+        }
+        buf.push(((v & 0x7F) | 0x80) as u8);
+        v >>= 7;
+    }
+}
+
+pub fn read_varint(buf: &[u8], pos: &mut usize) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut result: u32 = 0;
+    for i in 0..5 {
+        let byte: u8 = *buf.get(*pos).ok_or_else(|| HaoleError::parse("VarIntの途中でパケットが終了しました。"))?;
+        *pos += 1;
+        result |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+    Err(HaoleError::parse("VarIntが5バイトを超えています。"))
+}
+
+async fn read_varint_from<R: AsyncRead + Unpin>(reader: &mut R) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut result: u32 = 0;
+    for i in 0..5 {
+        let byte: u8 = reader.read_u8().await?;
+        result |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(result as i32);
+        }
+    }
+    Err(HaoleError::parse("VarIntが5バイトを超えています。"))
+}
+
+// 長さ (VarInt) 付きの文字列を読む。負の長さや、パケットの外まで続く長さはエラーにする
+fn read_string<'a>(buf: &'a [u8], pos: &mut usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+    let len: i32 = read_varint(buf, pos)?;
+    let end: Option<usize> = usize::try_from(len).ok().and_then(|len: usize| pos.checked_add(len));
+    let s: &[u8] = end.and_then(|end: usize| buf.get(*pos..end)).ok_or_else(|| HaoleError::parse(format!("不正な文字列長です: {}", len)))?;
+    *pos += s.len();
+    Ok(s)
+}
+
+fn write_string(buf: &mut Vec<u8>, s: &str) {
+    write_varint(buf, s.len() as i32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+pub fn frame(packet_id: i32, payload: &[u8]) -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::with_capacity(payload.len() + 1);
+    write_varint(&mut body, packet_id);
+    body.extend_from_slice(payload);
+
+    let mut out: Vec<u8> = Vec::with_capacity(body.len() + 3);
+    write_varint(&mut out, body.len() as i32);
+    out.extend_from_slice(&body);
+    out
+}
+
+pub async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(i32, Vec<u8>), Box<dyn std::error::Error>> {
+    let len: i32 = read_varint_from(reader).await?;
+    if len <= 0 || len as usize > MAX_PACKET_LEN {
+        return Err(HaoleError::parse(format!("不正なパケット長です: {}", len)));
+    }
+    let mut body: Vec<u8> = vec![0; len as usize];
+    reader.read_exact(&mut body).await?;
+
+    let mut pos: usize = 0;
+    let packet_id: i32 = read_varint(&body, &mut pos)?;
+    Ok((packet_id, body[pos..].to_vec()))
+}
+
+fn timed_out(host: &str, port: u16) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!("{}:{} への接続がタイムアウトしました。", host, port),
+    )
+}
+
+// 接続はできたが、最新の形式の応答が得られなかったか (途中で切断された・解釈できない応答が返った)。
+// 接続できない・名前解決できない・タイムアウトした場合は、旧形式で問い合わせても結果は同じなので false
+pub fn is_handshake_failure(err: &(dyn std::error::Error + 'static)) -> bool {
+    if err.is::<HaoleError>() || err.is::<serde_json::Error>() {
+        return true;
+    }
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|e: &std::io::Error| matches!(e.kind(), std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::ConnectionReset))
+}
+
+pub async fn status(host: &str, port: u16) -> Result<SlpResponse, Box<dyn std::error::Error>> {
+    timeout(IO_TIMEOUT, status_inner(host, port))
+        .await
+        .map_err(|_| timed_out(host, port))?
+}
+
+async fn status_inner(host: &str, port: u16) -> Result<SlpResponse, Box<dyn std::error::Error>> {
+    let mut stream: TcpStream = TcpStream::connect((host, port)).await?;
+    let address: SocketAddr = stream.peer_addr()?;
+
+    let mut handshake: Vec<u8> = Vec::new();
+    write_varint(&mut handshake, HANDSHAKE_PROTOCOL);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+    stream.write_all(&frame(0x00, &handshake)).await?;
+    stream.write_all(&frame(0x00, &[])).await?;
+
+    let (packet_id, body) = read_packet(&mut stream).await?;
+    if packet_id != 0x00 {
+        return Err(HaoleError::parse(format!("Status Response ではないパケットを受信しました: {:#04x}", packet_id)));
+    }
+    let mut pos: usize = 0;
+    let json: &[u8] = read_string(&body, &mut pos)?;
+    let status: SlpStatus = serde_json::from_slice(json)?;
+
+    let payload: i64 = chrono::Utc::now().timestamp_millis();
+    let sent: Instant = Instant::now();
+    stream.write_all(&frame(0x01, &payload.to_be_bytes())).await?;
+    let (packet_id, body) = read_packet(&mut stream).await?;
+    let latency: Duration = sent.elapsed();
+    if packet_id != 0x01 || body != payload.to_be_bytes() {
+        return Err(HaoleError::parse("Pong の内容が Ping と一致しません。"));
+    }
+
+    Ok(SlpResponse { status, latency, address })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn fake_server(listener: TcpListener, response: &'static str) {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        let (packet_id, body) = read_packet(&mut socket).await.unwrap();
+        assert_eq!(packet_id, 0x00);
+        let mut pos: usize = 0;
+        assert_eq!(read_varint(&body, &mut pos).unwrap(), HANDSHAKE_PROTOCOL);
+        assert_eq!(read_string(&body, &mut pos).unwrap(), b"127.0.0.1");
+        assert_eq!(*body.last().unwrap(), 1);
+
+        let (packet_id, body) = read_packet(&mut socket).await.unwrap();
+        assert_eq!((packet_id, body.len()), (0x00, 0));
+
+        let mut payload: Vec<u8> = Vec::new();
+        write_string(&mut payload, response);
+        socket.write_all(&frame(0x00, &payload)).await.unwrap();
+
+        let (packet_id, body) = read_packet(&mut socket).await.unwrap();
+        assert_eq!(packet_id, 0x01);
+        socket.write_all(&frame(0x01, &body)).await.unwrap();
+    }
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 127, 128, 255, 25565, 2097151, i32::MAX, -1, i32::MIN] {
+            let mut buf: Vec<u8> = Vec::new();
+            write_varint(&mut buf, value);
+            let mut pos: usize = 0;
+            assert_eq!(read_varint(&buf, &mut pos).unwrap(), value);
+            assert_eq!(pos, buf.len());
+        }
+        let mut buf: Vec<u8> = Vec::new();
+        write_varint(&mut buf, -1);
+        assert_eq!(buf, [0xff, 0xff, 0xff, 0xff, 0x0f]);
+    }
+
+    #[test]
+    fn rejects_bad_string_lengths() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_string(&mut buf, "{}");
+        let mut pos: usize = 0;
+        assert_eq!(read_string(&buf, &mut pos).unwrap(), b"{}");
+        assert_eq!(pos, buf.len());
+
+        // 負の長さ・パケットより長い長さ
+        for len in [-1, i32::MIN, 3, i32::MAX] {
+            let mut buf: Vec<u8> = Vec::new();
+            write_varint(&mut buf, len);
+            buf.extend_from_slice(b"{}");
+            assert!(read_string(&buf, &mut 0).is_err(), "{}", len);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_handshake_failures_allow_legacy_fallback() {
+        // 接続できないポートでは旧形式を試さない
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        drop(listener);
+        assert!(!is_handshake_failure(status("127.0.0.1", port).await.unwrap_err().as_ref()));
+
+        // 接続後すぐに切断する旧バージョンのサーバー
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        let server: tokio::task::JoinHandle<()> = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            drop(socket);
+        });
+        assert!(is_handshake_failure(status("127.0.0.1", port).await.unwrap_err().as_ref()));
+        server.await.unwrap();
+
+        assert!(is_handshake_failure(HaoleError::parse("bad").as_ref()));
+        assert!(!is_handshake_failure(&std::io::Error::from(std::io::ErrorKind::TimedOut)));
+    }
+
+    #[tokio::test]
+    async fn status_against_fake_listener() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        let server: tokio::task::JoinHandle<()> = tokio::spawn(fake_server(listener, r#"{
+            "version": {"name": "Paper 1.21.4", "protocol": 769},
+            "players": {"max": 100, "online": 2, "sample": [{"name": "Steve", "id": "0"}, {"name": "Alex", "id": "1"}]},
+            "description": {"text": "Haven", "color": "gold", "extra": [{"text": "MC", "bold": true}]}
+        }"#));
+
+        let resp: SlpResponse = status("127.0.0.1", port).await.unwrap();
+        server.await.unwrap();
+
+        assert_eq!(resp.status.version.name, "Paper 1.21.4");
+        assert_eq!(resp.status.version.protocol, 769);
+        let players: SlpPlayers = resp.status.players.unwrap();
+        assert_eq!((players.online, players.max), (2, 100));
+        let names: Vec<String> = players.sample.unwrap().into_iter().map(|s: SlpSample| s.name).collect();
+        assert_eq!(names, ["Steve", "Alex"]);
+        assert_eq!(crate::motd::to_legacy(&resp.status.description), "§6Haven§lMC");
+        assert_eq!(resp.address.port(), port);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::config::ServerProfile;
+use crate::history::{self, Sample, Since, Store};
+use crate::incidents::{self, Incident};
+use crate::output::{self, OutputFormat};
+use chrono::{DateTime, Local, Timelike};
+use colored::*;
+use serde_json::json;
+
+pub struct Summary {
+    pub samples: usize,
+    pub peak: Option<(u32, DateTime<Local>)>,
+    pub min: Option<(u32, DateTime<Local>)>,
+    pub average: Option<f64>,
+    pub median: Option<f64>,
+    pub uptime_percent: f64,
+    pub outages: usize,
+    pub busiest_hour: Option<(u32, f64)>,
+}
+
+impl Summary {
+    pub fn from_samples(samples: &[Sample], now: DateTime<Local>) -> Self {
+        let online: Vec<&Sample> = samples.iter().filter(|s: &&Sample| s.online).collect();
+
+        // 同じ値が複数回あった場合は最初に記録された時刻を使う
+        let mut peak: Option<(u32, DateTime<Local>)> = None;
+        let mut min: Option<(u32, DateTime<Local>)> = None;
+        for s in &online {
+            if peak.is_none_or(|(n, _)| s.players_online > n) {
+                peak = Some((s.players_online, s.time));
+            }
+            if min.is_none_or(|(n, _)| s.players_online < n) {
+                min = Some((s.players_online, s.time));
+            }
+        }
+
+        let mut counts: Vec<u32> = online.iter().map(|s: &&Sample| s.players_online).collect();
+        counts.sort_unstable();
+        let average: Option<f64> = (!counts.is_empty()).then(|| counts.iter().sum::<u32>() as f64 / counts.len() as f64);
+        let median: Option<f64> = match counts.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(counts[n / 2] as f64),
+            n => Some((counts[n / 2 - 1] + counts[n / 2]) as f64 / 2.0),
+        };
+
+        // 稼働率と停止回数は incidents と同じく、障害の一覧から求める
+        let incidents: Vec<Incident> = incidents::from_history(samples);
+        let uptime_percent: f64 = match samples.first() {
+            Some(first) => incidents::uptime_percent(&incidents, first.time, now),
+            None => 0.0,
+        };
+        let outages: usize = incidents.len();
+
+        let mut hours: [(u64, u64); 24] = [(0, 0); 24];
+        for s in &online {
+            let slot: &mut (u64, u64) = &mut hours[s.time.hour() as usize];
+            slot.0 += s.players_online as u64;
+            slot.1 += 1;
+        }
+        let busiest_hour: Option<(u32, f64)> = hours
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, n))| *n > 0)
+            .map(|(hour, (sum, n))| (hour as u32, *sum as f64 / *n as f64))
+            .fold(None, |best: Option<(u32, f64)>, cur: (u32, f64)| match best {
+                Some(b) if b.1 >= cur.1 => Some(b),
+                _ => Some(cur),
+            });
+
+        Self {
+            samples: samples.len(),
+            peak,
+            min,
+            average,
+            median,
+            uptime_percent,
+            outages,
+            busiest_hour,
+        }
+    }
+}
+
+pub fn run(target: &ServerProfile, since: &Since, format: OutputFormat) -> Result<bool, Box<dyn std::error::Error>> {
+    let store: Store = Store::open(&target.name)?;
+    let samples: Vec<Sample> = store.load(Some(since.start()))?;
+    let summary: Summary = Summary::from_samples(&samples, Local::now());
+
+    if format.is_structured() {
+        let point = |p: Option<(u32, DateTime<Local>)>| p.map(|(n, t)| json!({ "players": n, "time": t.to_rfc3339() }));
+        output::emit(format, "stats", &target.name, json!({
+            "since": since.label,
+            "samples": summary.samples,
+            "peak": point(summary.peak),
+            "min": point(summary.min),
+            "average": summary.average,
+            "median": summary.median,
+            "uptime_percent": summary.uptime_percent,
+            "outages": summary.outages,
+            "busiest_hour": summary.busiest_hour.map(|(hour, avg)| json!({ "hour": hour, "average": avg })),
+        }))?;
+        return Ok(summary.samples > 0);
+    }
+
+    if summary.samples == 0 {
+        history::print_no_records(&since.label);
+        return Ok(false);
+    }
+
+    println!("{} 直近 {} の統計 ({} 件の記録)\n", ">>".blue(), since.label, summary.samples);
+    if let Some((n, t)) = summary.peak {
+        println!("最大: {} 人 ({})", n.to_string().green(), history::format_time(t));
+    }
+    if let Some((n, t)) = summary.min {
+        println!("最小: {} 人 ({})", n.to_string().yellow(), history::format_time(t));
+    }
+    if let (Some(avg), Some(median)) = (summary.average, summary.median) {
+        println!("平均: {:.1} 人 / 中央値: {:.1} 人", avg, median);
+    }
+    println!("稼働率: {:.2}% (停止 {} 回)", summary.uptime_percent, summary.outages);
+    if let Some((hour, avg)) = summary.busiest_hour {
+        println!("最も混む時間帯: {:02}:00-{:02}:59 (平均 {:.1} 人)", hour, hour, avg);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::incidents::Cause;
+
+    #[test]
+    fn summarizes_samples() {
+        let samples: Vec<Sample> = vec![
+            Sample::at(1, 10, 0).with_count(4),
+            Sample::at(1, 11, 0).with_count(10),
+            Sample::at(1, 12, 0).down(Cause::Unknown),
+            Sample::at(1, 13, 0).with_count(2),
+            Sample::at(1, 14, 0).down(Cause::Unknown),
+            Sample::at(1, 15, 0).down(Cause::Unknown),
+            Sample::at(1, 16, 0).with_count(6),
+        ];
+        let s: Summary = Summary::from_samples(&samples, Sample::at(1, 17, 0).time);
+        assert_eq!(s.peak.map(|p| p.0), Some(10));
+        assert_eq!(s.min.map(|p| (p.0, p.1.hour())), Some((2, 13)));
+        assert_eq!(s.average, Some(5.5));
+        assert_eq!(s.median, Some(5.0));
+        assert_eq!(s.outages, 2);
+        // 10時から17時までの7時間のうち、12時台と14〜15時台の3時間停止
+        assert!((s.uptime_percent - 400.0 / 7.0).abs() < 1e-9);
+        assert_eq!(s.busiest_hour, Some((11, 10.0)));
+    }
+}
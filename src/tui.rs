@@ -0,0 +1,1030 @@
+use crate::bedrock::BedrockStatus;
+use crate::config::{Edition, ServerProfile};
+use crate::events::{EventKind, EventTracker, PlayerEvent};
+use crate::heatmap::{self, Heatmap};
+use crate::history::{self, Sample, Store};
+use crate::icon::Icon;
+use crate::playtime::{PlayerRecord, Playtime};
+use crate::provider::{Answer, Details, Source};
+use crate::{friends, motd, HavenStatus, McStatusIOResponse};
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    symbols::Marker,
+    widgets::{Axis, Block, Borders, Chart, Clear as ClearWidget, Dataset, GraphType, HighlightSpacing, List, ListItem, ListState, Paragraph, Tabs, Wrap},
+    Frame, Terminal,
+};
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+const HISTORY_LEN: usize = 50;
+const RETENTION_DAYS: i64 = 7;
+// 起動時に読み込む履歴の日数。グラフとヒートマップの期間の長いほう
+const LOAD_DAYS: i64 = if RETENTION_DAYS > heatmap::DEFAULT_WEEKS as i64 * 7 { RETENTION_DAYS } else { heatmap::DEFAULT_WEEKS as i64 * 7 };
+// グラフに描く点の最大数。これを超える分はまとめて間引く
+const CHART_POINTS: usize = 300;
+const TIMELINE_GAP_MINUTES: i64 = 10;
+const EVENT_LOG_LEN: usize = 100;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const FRAME_INTERVAL: Duration = Duration::from_millis(200);
+const PAGE_SIZE: u16 = 10;
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Overview,
+    Players,
+    ServerInfo,
+    History,
+    Events,
+    Heatmap,
+}
+
+const TABS: [Tab; 6] = [Tab::Overview, Tab::Players, Tab::ServerInfo, Tab::History, Tab::Events, Tab::Heatmap];
+
+impl Tab {
+    fn title(self) -> &'static str {
+        match self {
+            Tab::Overview => "Overview",
+            Tab::Players => "Players",
+            Tab::ServerInfo => "Server Info",
+            Tab::History => "History",
+            Tab::Events => "Events",
+            Tab::Heatmap => "Heatmap",
+        }
+    }
+
+    fn index(self) -> usize {
+        TABS.iter().position(|t: &Tab| *t == self).unwrap_or(0)
+    }
+
+    fn next(self) -> Self {
+        TABS[(self.index() + 1) % TABS.len()]
+    }
+
+    fn prev(self) -> Self {
+        TABS[(self.index() + TABS.len() - 1) % TABS.len()]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PlayerSort {
+    Name,
+    Joined,
+    Session,
+}
+
+impl PlayerSort {
+    fn label(self) -> &'static str {
+        match self {
+            PlayerSort::Name => "名前順",
+            PlayerSort::Joined => "参加順",
+            PlayerSort::Session => "滞在時間順",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            PlayerSort::Name => PlayerSort::Joined,
+            PlayerSort::Joined => PlayerSort::Session,
+            PlayerSort::Session => PlayerSort::Name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Zoom {
+    FiveMinutes,
+    Hour,
+    Day,
+    Week,
+}
+
+impl Zoom {
+    fn label(self) -> &'static str {
+        match self {
+            Zoom::FiveMinutes => "直近5分",
+            Zoom::Hour => "直近1時間",
+            Zoom::Day => "直近24時間",
+            Zoom::Week => "直近7日",
+        }
+    }
+
+    fn duration(self) -> chrono::Duration {
+        match self {
+            Zoom::FiveMinutes => chrono::Duration::minutes(5),
+            Zoom::Hour => chrono::Duration::hours(1),
+            Zoom::Day => chrono::Duration::days(1),
+            Zoom::Week => chrono::Duration::days(RETENTION_DAYS),
+        }
+    }
+
+    fn time_format(self) -> &'static str {
+        match self {
+            Zoom::FiveMinutes => "%H:%M:%S",
+            Zoom::Hour | Zoom::Day => "%H:%M",
+            Zoom::Week => "%m/%d %H:%M",
+        }
+    }
+
+    fn zoom_in(self) -> Self {
+        match self {
+            Zoom::FiveMinutes | Zoom::Hour => Zoom::FiveMinutes,
+            Zoom::Day => Zoom::Hour,
+            Zoom::Week => Zoom::Day,
+        }
+    }
+
+    fn zoom_out(self) -> Self {
+        match self {
+            Zoom::FiveMinutes => Zoom::Hour,
+            Zoom::Hour => Zoom::Day,
+            Zoom::Day | Zoom::Week => Zoom::Week,
+        }
+    }
+}
+
+enum Control {
+    Refresh,
+    TogglePause,
+}
+
+struct Update {
+    st: Option<HavenStatus>,
+    sample: Sample,
+    bedrock_st: Option<BedrockStatus>,
+    info: Option<McStatusIOResponse>,
+    motd: Option<Vec<motd::Span>>,
+    // サーバーに問い合わせられなければ None、アイコンがないサーバーなら Some(None)
+    icon: Option<Option<Icon>>,
+}
+
+enum PollEvent {
+    Started,
+    Finished(Box<Update>),
+    Paused(bool),
+}
+
+async fn fetch_update(target: &ServerProfile, store: &Store, need_icon: bool) -> Update {
+    let (answer, sample) = crate::record_history(target, store).await;
+    // Bedrock の RakNet は応答がないと再送を待つため、Bedrock のプロファイルでだけ問い合わせる
+    let bedrock_st: Option<BedrockStatus> = match target.edition {
+        Edition::Bedrock => crate::fetch_bedrock_status(target).await.ok(),
+        Edition::Java => None,
+    };
+
+    // Server Info タブと MOTD・アイコンは、取得元の応答を使い回す
+    let (st, details): (Option<HavenStatus>, Option<Details>) = match answer {
+        Some(Answer { status, details: Some(details), .. }) => (Some(status), Some(details)),
+        // HavenMC API は詳細を返さないため、残りの取得元に問い合わせる
+        Some(answer) => (Some(answer.status), crate::provider::fetch_details(target).await.ok().map(|(_, details): (Source, Details)| details)),
+        // すべての取得元が失敗したときは、同じ問い合わせを繰り返さない
+        None => (None, None),
+    };
+    let (info, motd, icon_uri) = match details {
+        Some(Details::Slp(resp)) => (
+            Some(McStatusIOResponse::from_slp(&target.host, &resp)),
+            Some(motd::parse_component(&resp.status.description)),
+            Some(resp.status.favicon),
+        ),
+        Some(Details::Mcstatus(info)) => {
+            let spans: Vec<motd::Span> = motd::parse_legacy(&info.motd.raw);
+            let icon_uri: Option<String> = info.icon.clone();
+            (Some(info), Some(spans), Some(icon_uri))
+        }
+        None => (None, None, None),
+    };
+    // 応答にアイコンがなかった (または読めなかった) 場合も、次からは取得し直さない
+    let icon: Option<Option<Icon>> = if need_icon {
+        icon_uri.map(|uri: Option<String>| uri.and_then(|u: String| Icon::from_data_uri(&u).ok()))
+    } else {
+        None
+    };
+
+    Update { st, sample, bedrock_st, info, motd, icon }
+}
+
+// 描画ループを止めないよう、ステータスの取得は別タスクで行い結果をチャネルで送る
+async fn poll_loop(target: ServerProfile, mut control: mpsc::UnboundedReceiver<Control>, events: mpsc::UnboundedSender<PollEvent>) {
+    let Ok(store) = Store::open(&target.name) else {
+        return;
+    };
+    let mut paused: bool = false;
+    let mut need_icon: bool = true;
+    loop {
+        if events.send(PollEvent::Started).is_err() {
+            return;
+        }
+        let update: Update = fetch_update(&target, &store, need_icon).await;
+        need_icon &= update.icon.is_none();
+        if events.send(PollEvent::Finished(Box::new(update))).is_err() {
+            return;
+        }
+
+        let sleep = tokio::time::sleep(POLL_INTERVAL);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep, if !paused => break,
+                ctrl = control.recv() => match ctrl {
+                    Some(Control::Refresh) => break,
+                    Some(Control::TogglePause) => {
+                        paused = !paused;
+                        let _ = events.send(PollEvent::Paused(paused));
+                        // 再開したらすぐに取得する
+                        if !paused {
+                            break;
+                        }
+                    }
+                    None => return,
+                },
+            }
+        }
+    }
+}
+
+// crossterm の入力待ちはブロックするため専用スレッドで読む
+fn spawn_input() -> mpsc::UnboundedReceiver<Event> {
+    let (tx, rx) = mpsc::unbounded_channel::<Event>();
+    std::thread::spawn(move || {
+        while !tx.is_closed() {
+            match crossterm::event::poll(Duration::from_millis(100)) {
+                Ok(true) => match crossterm::event::read() {
+                    Ok(ev) => {
+                        if tx.send(ev).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                },
+                Ok(false) => {}
+                Err(_) => break,
+            }
+        }
+    });
+    rx
+}
+
+struct App<'a> {
+    target: &'a ServerProfile,
+    friends: &'a [String],
+    tab: Tab,
+    fetching: bool,
+    paused: bool,
+    last_updated: Option<Instant>,
+    frame: usize,
+    players: ListState,
+    sort: PlayerSort,
+    filter: String,
+    filtering: bool,
+    playtime: Playtime,
+    // 詳細ポップアップを開いているプレイヤー
+    detail: Option<String>,
+    zoom: Zoom,
+    heatmap: Heatmap,
+    st: Option<HavenStatus>,
+    bedrock_st: Option<BedrockStatus>,
+    info: Option<McStatusIOResponse>,
+    motd: Vec<motd::Span>,
+    icon: Option<Icon>,
+    history: VecDeque<Sample>,
+    tracker: EventTracker,
+    event_log: VecDeque<PlayerEvent>,
+}
+
+impl<'a> App<'a> {
+    fn new(target: &'a ServerProfile, friends: &'a [String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let store: Store = Store::open(&target.name)?;
+        // 前回までの記録のうち、グラフとヒートマップで使う期間だけを読み込む
+        let samples: Vec<Sample> = store.load(Some(Local::now() - chrono::Duration::days(LOAD_DAYS)))?;
+        Ok(Self::from_history(target, friends, samples))
+    }
+
+    fn from_history(target: &'a ServerProfile, friends: &'a [String], samples: Vec<Sample>) -> Self {
+        let playtime: Playtime = Playtime::from_history(&samples);
+        let weeks_ago: DateTime<Local> = Local::now() - chrono::Duration::weeks(heatmap::DEFAULT_WEEKS as i64);
+        let heatmap: Heatmap = Heatmap::from_samples(&samples.iter().filter(|s: &&Sample| s.time >= weeks_ago).cloned().collect::<Vec<Sample>>());
+        let cutoff: DateTime<Local> = Local::now() - chrono::Duration::days(RETENTION_DAYS);
+        let history: VecDeque<Sample> = samples.into_iter().filter(|s: &Sample| s.time >= cutoff).collect();
+        let mut tracker: EventTracker = EventTracker::default();
+        if let Some(last) = history.back() {
+            tracker.update(last);
+        }
+        Self {
+            target,
+            friends,
+            tab: Tab::Overview,
+            fetching: false,
+            paused: false,
+            last_updated: None,
+            frame: 0,
+            players: ListState::default(),
+            sort: PlayerSort::Name,
+            filter: String::new(),
+            filtering: false,
+            playtime,
+            detail: None,
+            zoom: Zoom::Hour,
+            heatmap,
+            st: None,
+            bedrock_st: None,
+            info: None,
+            motd: Vec::new(),
+            icon: None,
+            history,
+            tracker,
+            event_log: VecDeque::with_capacity(EVENT_LOG_LEN),
+        }
+    }
+
+    fn apply(&mut self, event: PollEvent) {
+        let update: Update = match event {
+            PollEvent::Started => {
+                self.fetching = true;
+                return;
+            }
+            PollEvent::Paused(paused) => {
+                self.paused = paused;
+                return;
+            }
+            PollEvent::Finished(update) => *update,
+        };
+        self.fetching = false;
+        self.last_updated = Some(Instant::now());
+        self.st = update.st;
+        self.bedrock_st = update.bedrock_st;
+        if update.info.is_some() {
+            self.info = update.info;
+        }
+        if let Some(spans) = update.motd {
+            self.motd = spans;
+        }
+        if let Some(icon) = update.icon {
+            self.icon = icon;
+        }
+
+        self.playtime.update(&update.sample);
+        self.heatmap.add(&update.sample);
+        for e in self.tracker.update(&update.sample) {
+            if self.event_log.len() >= EVENT_LOG_LEN {
+                self.event_log.pop_front();
+            }
+            self.event_log.push_back(e);
+        }
+        let cutoff: DateTime<Local> = update.sample.time - chrono::Duration::days(RETENTION_DAYS);
+        while self.history.front().is_some_and(|s: &Sample| s.time < cutoff) {
+            self.history.pop_front();
+        }
+        self.history.push_back(update.sample);
+    }
+
+    // 絞り込みと並び替えを適用した一覧 (フレンドは常に先頭)
+    fn visible_players(&self) -> Vec<String> {
+        let Some(list) = self.st.as_ref().and_then(|s: &HavenStatus| s.players.list.as_ref()) else {
+            return Vec::new();
+        };
+        let filter: String = self.filter.to_lowercase();
+        let mut players: Vec<String> = list.iter().filter(|p: &&String| p.to_lowercase().contains(&filter)).cloned().collect();
+        let joined = |p: &String| self.playtime.get(p).and_then(|r: &PlayerRecord| r.current);
+        match self.sort {
+            PlayerSort::Name => players.sort_by_key(|p: &String| p.to_lowercase()),
+            // 新しく参加した順。参加時刻が分からないプレイヤーは最後に並べる
+            PlayerSort::Joined => players.sort_by_key(|p: &String| std::cmp::Reverse(joined(p))),
+            PlayerSort::Session => players.sort_by_key(|p: &String| (joined(p).is_none(), joined(p))),
+        }
+        friends::sort_friends_first(self.friends, &mut players);
+        players
+    }
+
+    // Players パネルが処理したキーなら true を返す
+    fn handle_players_key(&mut self, key: KeyEvent) -> bool {
+        if self.detail.is_some() {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                self.detail = None;
+            }
+            return true;
+        }
+        if !matches!(self.tab, Tab::Overview | Tab::Players) {
+            return false;
+        }
+        if self.filtering {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.filter.clear();
+                }
+                _ => return false,
+            }
+            self.players.select_first();
+            return true;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.players.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.players.select_next(),
+            KeyCode::PageUp => self.players.scroll_up_by(PAGE_SIZE),
+            KeyCode::PageDown => self.players.scroll_down_by(PAGE_SIZE),
+            KeyCode::Home => self.players.select_first(),
+            KeyCode::End => self.players.select_last(),
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Enter => {
+                let players: Vec<String> = self.visible_players();
+                self.detail = self.players.selected().and_then(|i: usize| players.get(i.min(players.len().saturating_sub(1)))).cloned();
+            }
+            KeyCode::Esc if !self.filter.is_empty() => self.filter.clear(),
+            _ => return false,
+        }
+        true
+    }
+
+    // 入力中の絞り込みは確定して、別のタブのキー操作を奪わないようにする
+    fn set_tab(&mut self, tab: Tab) {
+        self.tab = tab;
+        self.filtering = false;
+    }
+
+    fn refresh_line(&self) -> Line<'_> {
+        let mut spans: Vec<Span<'_>> = vec![Span::raw(" ")];
+        if self.fetching {
+            spans.push(Span::styled(format!("{} 更新中...", SPINNER[self.frame % SPINNER.len()]), Style::default().fg(Color::Cyan)));
+        } else if let Some(t) = self.last_updated {
+            spans.push(Span::styled(format!("最終更新: {}秒前", t.elapsed().as_secs()), Style::default().fg(Color::DarkGray)));
+        }
+        if self.paused {
+            spans.push(Span::styled(" [一時停止中]", Style::default().fg(Color::Yellow)));
+        }
+        spans.push(Span::styled("  r: 更新  p: 一時停止/再開", Style::default().fg(Color::DarkGray)));
+        Line::from(spans)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>) {
+        let chunks: Rc<[Rect]> = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(6),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(f.area());
+
+        self.draw_header(f, chunks[0]);
+
+        let titles: Vec<String> = TABS.iter().enumerate().map(|(i, t)| format!("{} {}", i + 1, t.title())).collect();
+        let tabs: Tabs<'_> = Tabs::new(titles)
+            .select(self.tab.index())
+            .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL).title(" 1-6/Tab: 切替  q: 終了 "));
+        f.render_widget(tabs, chunks[1]);
+
+        match self.tab {
+            Tab::Overview => self.draw_overview(f, chunks[2]),
+            Tab::Players => self.draw_players(f, chunks[2]),
+            Tab::ServerInfo => self.draw_server_info(f, chunks[2]),
+            Tab::History => self.draw_history(f, chunks[2], true),
+            Tab::Events => f.render_widget(self.events_list(), chunks[2]),
+            Tab::Heatmap => self.draw_heatmap(f, chunks[2]),
+        }
+        if let Some(name) = &self.detail {
+            self.draw_player_detail(f, name);
+        }
+    }
+
+    fn draw_player_detail(&self, f: &mut Frame<'_>, name: &str) {
+        let label = |name: &'static str, value: String| Line::from(vec![
+            Span::styled(format!(" {}: ", name), Style::default().fg(Color::Cyan)),
+            Span::raw(value),
+        ]);
+        let time = |t: DateTime<Local>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+        let lines: Vec<Line<'_>> = match self.playtime.get(name) {
+            Some(r) => vec![
+                label("現在のセッション", r.session_length(Local::now()).map_or("オフライン".to_string(), history::format_duration)),
+                label("初めて確認", time(r.first_seen)),
+                label("最後に確認", time(r.last_seen)),
+                label("累計プレイ時間", history::format_duration(r.total)),
+                label("セッション数", r.sessions.to_string()),
+            ],
+            None => vec![Line::from(Span::styled(" このプレイヤーの記録はまだありません。", Style::default().fg(Color::Yellow)))],
+        };
+
+        let area: Rect = f.area();
+        let (width, height) = (area.width.min(50), area.height.min(lines.len() as u16 + 2));
+        let popup: Rect = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        let title: String = if friends::is_friend(self.friends, name) { format!(" ★ {} ", name) } else { format!(" {} ", name) };
+        let detail: Paragraph<'_> = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title).title_bottom(" Esc: 閉じる "));
+        f.render_widget(ClearWidget, popup);
+        f.render_widget(detail, popup);
+    }
+
+    fn draw_header(&self, f: &mut Frame<'_>, area: Rect) {
+        let header: Rc<[Rect]> = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(10),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        let online = |on: bool| if on {
+            Span::styled("ONLINE", Style::default().fg(Color::Green))
+        } else {
+            Span::styled("OFFLINE", Style::default().fg(Color::Red))
+        };
+        let status_line: Line<'_> = match &self.st {
+            Some(s) => Line::from(vec![
+                Span::raw(" サーバー: "),
+                online(s.online),
+                Span::raw(format!(" | オンライン: {}/{}", s.players.online, s.players.max)),
+            ]),
+            None => Line::from(vec![
+                Span::styled(" !!", Style::default().fg(Color::Yellow)),
+                Span::raw(" データを取得中、または接続エラー..."),
+            ]),
+        };
+        let bedrock_line: Line<'_> = match &self.bedrock_st {
+            Some(b) => Line::from(vec![
+                Span::raw(" Bedrock: "),
+                online(true),
+                Span::raw(format!(" | オンライン: {}/{} | {} | {}ms", b.online, b.max, b.version, b.latency.as_millis())),
+            ]),
+            None if self.target.edition == Edition::Bedrock => Line::from(vec![Span::raw(" Bedrock: "), online(false)]),
+            None => Line::from(vec![Span::raw(" Bedrock: "), Span::styled("確認しません (edition = \"java\")", Style::default().fg(Color::DarkGray))]),
+        };
+        let status_bar: Paragraph<'_> = Paragraph::new(vec![status_line, bedrock_line, Line::default(), self.refresh_line()])
+            .block(Block::default().borders(Borders::ALL).title(format!(" {} Status ", self.target.name)));
+
+        let icon_panel: Paragraph<'_> = Paragraph::new(self.icon.as_ref().map_or(vec![], |i: &Icon| i.to_lines(8)))
+            .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(icon_panel, header[0]);
+        f.render_widget(status_bar, header[1]);
+    }
+
+    fn draw_overview(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let main_layout: Rc<[Rect]> = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(30),
+                Constraint::Percentage(70),
+            ])
+            .split(area);
+
+        let left_chunks: Rc<[Rect]> = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(60),
+                Constraint::Percentage(40),
+            ])
+            .split(main_layout[0]);
+
+        self.draw_players(f, left_chunks[0]);
+        f.render_widget(self.events_list(), left_chunks[1]);
+        self.draw_history(f, main_layout[1], false);
+    }
+
+    fn draw_players(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let total: usize = self.st.as_ref().and_then(|s: &HavenStatus| s.players.list.as_ref()).map_or(0, |l: &Vec<String>| l.len());
+        let now: DateTime<Local> = Local::now();
+        let players: Vec<String> = self.visible_players();
+        let shown: usize = players.len();
+        let players_items: Vec<ListItem<'static>> = players.into_iter().map(|p: String| {
+            let session: String = self
+                .playtime
+                .get(&p)
+                .and_then(|r: &PlayerRecord| r.session_length(now))
+                .map_or(String::new(), |d: chrono::Duration| format!("  {}", history::format_duration(d)));
+            if friends::is_friend(self.friends, &p) {
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("★ {}", p), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    Span::styled(session, Style::default().fg(Color::DarkGray)),
+                ]))
+            } else {
+                ListItem::new(Line::from(vec![Span::raw(format!("• {}", p)), Span::styled(session, Style::default().fg(Color::DarkGray))]))
+            }
+        }).collect();
+
+        let footer: String = if self.filtering || !self.filter.is_empty() {
+            format!(" /{}{} ", self.filter, if self.filtering { "▏" } else { "" })
+        } else {
+            " /: 絞り込み  s: 並び替え ".to_string()
+        };
+        let list: List<'static> = List::new(players_items)
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Players ({}/{} 表示) [{}] ", shown, total, self.sort.label()))
+                .title_bottom(footer))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ")
+            .highlight_spacing(HighlightSpacing::Always);
+        // 一覧が空のあいだは選択が外れるので、表示できるようになったら先頭を選び直す
+        if self.players.selected().is_none() && shown > 0 {
+            self.players.select_first();
+        }
+        f.render_stateful_widget(list, area, &mut self.players);
+    }
+
+    fn events_list(&self) -> List<'_> {
+        let event_items: Vec<ListItem> = self.event_log.iter().rev()
+            .map(|e: &PlayerEvent| {
+                let color: Color = match e.kind {
+                    EventKind::Join => Color::Green,
+                    EventKind::Leave => Color::Red,
+                };
+                ListItem::new(format!(" {}", e.line())).style(Style::default().fg(color))
+            })
+            .collect();
+        List::new(event_items)
+            .block(Block::default().borders(Borders::ALL).title(" Events "))
+    }
+
+    fn draw_history(&self, f: &mut Frame<'_>, area: Rect, full: bool) {
+        let right_chunks: Rc<[Rect]> = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(if full { 60 } else { 50 }),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        let history_content: Vec<ListItem> = self.history.iter().rev().take(HISTORY_LEN)
+            .map(|e: &Sample| {
+                let state: &str = if e.online { "" } else { " (offline)" };
+                ListItem::new(format!(" [{}] {} players{}", e.time.format("%H:%M:%S"), e.players_online, state))
+            })
+            .collect();
+        let history_list: List<'_> = List::new(history_content)
+            .block(Block::default().borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM));
+
+        self.draw_chart(f, right_chunks[0]);
+        self.draw_timeline(f, right_chunks[1]);
+        f.render_widget(history_list, right_chunks[2]);
+    }
+
+    // グラフと同じ範囲を1文字ずつの区間に分け、稼働 (緑)・障害 (赤)・記録なし (灰) で塗る
+    fn draw_timeline(&self, f: &mut Frame<'_>, area: Rect) {
+        let block: Block<'_> = Block::default().borders(Borders::LEFT | Borders::RIGHT);
+        let inner: Rect = block.inner(area);
+        let label: Span<'_> = Span::styled(" 障害 ", Style::default().fg(Color::DarkGray));
+        let width: usize = (inner.width as usize).saturating_sub(label.width());
+        let end: DateTime<Local> = Local::now();
+        let start: DateTime<Local> = end - self.zoom.duration();
+        let slot: f64 = self.zoom.duration().num_milliseconds() as f64 / width.max(1) as f64;
+
+        // 各サンプルの状態は次のサンプルまで (最大 TIMELINE_GAP_MINUTES 分) 続いたものとみなす
+        let mut cells: Vec<Option<bool>> = vec![None; width];
+        let samples: Vec<&Sample> = self.history.iter().collect();
+        for (i, s) in samples.iter().enumerate() {
+            let until: DateTime<Local> = samples
+                .get(i + 1)
+                .map_or(end, |next: &&Sample| next.time)
+                .min(s.time + chrono::Duration::minutes(TIMELINE_GAP_MINUTES));
+            if until < start {
+                continue;
+            }
+            let slot_of = |t: DateTime<Local>| ((t.max(start) - start).num_milliseconds() as f64 / slot) as usize;
+            for cell in cells.iter_mut().take(slot_of(until) + 1).skip(slot_of(s.time)) {
+                *cell = Some(cell.unwrap_or(true) && s.online);
+            }
+        }
+
+        let mut spans: Vec<Span<'_>> = vec![label];
+        spans.extend(cells.into_iter().map(|cell: Option<bool>| match cell {
+            Some(true) => Span::styled("▆", Style::default().fg(Color::Green)),
+            Some(false) => Span::styled("█", Style::default().fg(Color::Red)),
+            None => Span::styled("·", Style::default().fg(Color::DarkGray)),
+        }));
+        f.render_widget(Paragraph::new(Line::from(spans)).block(block), area);
+    }
+
+    fn draw_chart(&self, f: &mut Frame<'_>, area: Rect) {
+        let end: DateTime<Local> = Local::now();
+        let start: DateTime<Local> = end - self.zoom.duration();
+        let span: f64 = self.zoom.duration().num_seconds() as f64;
+        let samples: Vec<&Sample> = self.history.iter().filter(|s: &&Sample| s.time >= start).collect();
+
+        // 点が多すぎるときは区間ごとの最大値にまとめる
+        let bucket: f64 = span / CHART_POINTS as f64;
+        let mut players: Vec<(f64, f64)> = Vec::new();
+        let mut last_slot: Option<usize> = None;
+        // 停止していた期間 (オフラインになった時刻から、次にオンラインを確認した時刻まで)
+        let mut periods: Vec<(f64, f64)> = Vec::new();
+        let mut down_since: Option<f64> = None;
+        for s in &samples {
+            let x: f64 = (s.time - start).num_seconds() as f64;
+            let y: f64 = s.players_online as f64;
+            let slot: usize = if samples.len() > CHART_POINTS { (x / bucket) as usize } else { players.len() };
+            match (last_slot, players.last_mut()) {
+                (Some(prev), Some(p)) if prev == slot => p.1 = p.1.max(y),
+                _ => players.push((x, y)),
+            }
+            last_slot = Some(slot);
+            match (s.online, down_since) {
+                (false, None) => down_since = Some(x),
+                (true, Some(from)) => {
+                    periods.push((from, x));
+                    down_since = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(from) = down_since {
+            periods.push((from, span));
+        }
+
+        let peak: u32 = samples.iter().map(|s: &&Sample| s.players_online).max().unwrap_or(0);
+        let max_players: Option<u32> = self.history.back().filter(|s: &&Sample| s.online).map(|s: &Sample| s.players_max);
+        let y_max: f64 = (max_players.unwrap_or(0).max(peak).max(10) as f64 * 1.1).ceil();
+        // 縦線を横に敷き詰めて停止期間を塗りつぶす (Braille は1文字に横2ドット)
+        let step: f64 = span / (area.width.max(1) as f64 * 2.0);
+        let outages: Vec<(f64, f64)> = periods
+            .iter()
+            .flat_map(|(from, to)| {
+                let n: usize = ((to - from) / step).ceil().max(1.0) as usize;
+                (0..=n).map(move |i: usize| ((from + step * i as f64).min(*to), y_max))
+            })
+            .collect();
+        let max_line: Vec<(f64, f64)> = max_players.map_or(vec![], |m: u32| vec![(0.0, m as f64), (span, m as f64)]);
+
+        let mut datasets: Vec<Dataset<'_>> = vec![
+            Dataset::default()
+                .name("停止")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Bar)
+                .style(Style::default().fg(Color::Red))
+                .data(&outages),
+        ];
+        if !max_line.is_empty() {
+            datasets.push(Dataset::default()
+                .name("最大人数")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::DarkGray))
+                .data(&max_line));
+        }
+        datasets.push(Dataset::default()
+            .name("プレイヤー")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&players));
+
+        let time = |t: DateTime<Local>| t.format(self.zoom.time_format()).to_string();
+        let chart: Chart<'_> = Chart::new(datasets)
+            .block(Block::default()
+                .borders(Borders::LEFT | Borders::RIGHT | Borders::TOP)
+                .title(format!(" Activity ({}) +/-: ズーム ", self.zoom.label())))
+            .x_axis(Axis::default()
+                .bounds([0.0, span])
+                .labels([time(start), time(start + self.zoom.duration() / 2), time(end)])
+                .style(Style::default().fg(Color::DarkGray)))
+            .y_axis(Axis::default()
+                .bounds([0.0, y_max])
+                .labels(["0".to_string(), format!("{}", (y_max / 2.0).round()), format!("{}", y_max)])
+                .style(Style::default().fg(Color::DarkGray)));
+        f.render_widget(chart, area);
+    }
+
+    fn draw_heatmap(&self, f: &mut Frame<'_>, area: Rect) {
+        let lines: Vec<Line<'static>> = if self.heatmap.is_empty() {
+            vec![Line::from(Span::styled(" まだ記録がありません。", Style::default().fg(Color::Yellow)))]
+        } else {
+            let mut lines: Vec<Line<'static>> = vec![Line::default()];
+            lines.extend(self.heatmap.to_lines());
+            lines
+        };
+        let heatmap: Paragraph<'_> = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" 曜日・時間帯別の平均プレイヤー数 (直近{}週間 / 最大 {:.1} 人) ", heatmap::DEFAULT_WEEKS, self.heatmap.max())),
+        );
+        f.render_widget(heatmap, area);
+    }
+
+    fn draw_server_info(&self, f: &mut Frame<'_>, area: Rect) {
+        let label = |name: &'static str, value: String| Line::from(vec![
+            Span::styled(format!(" {:<10}", name), Style::default().fg(Color::Cyan)),
+            Span::raw(value),
+        ]);
+        let mut lines: Vec<Line<'_>> = match &self.info {
+            Some(info) => vec![
+                label("Host", info.host.clone()),
+                label("IP", info.ip_address.clone()),
+                label("Port", info.port.to_string()),
+                label("Protocol", info.version.protocol.to_string()),
+                label("Version", self.st.as_ref().map_or("-".to_string(), |s: &HavenStatus| s.version.clone())),
+            ],
+            None => vec![Line::from(Span::styled(" サーバー情報を取得できませんでした。", Style::default().fg(Color::Yellow)))],
+        };
+        lines.push(Line::default());
+        lines.push(Line::from(Span::styled(" MOTD", Style::default().fg(Color::Cyan))));
+        lines.extend(motd::to_lines(&self.motd).into_iter().map(|mut line: Line<'static>| {
+            line.spans.insert(0, Span::raw(" "));
+            line
+        }));
+
+        let info: Paragraph<'_> = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(" Server Info "));
+        f.render_widget(info, area);
+    }
+}
+
+fn enter_terminal() -> std::io::Result<()> {
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen, Hide)
+}
+
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(std::io::stdout(), LeaveAlternateScreen, Show);
+}
+
+// 生きている間だけ端末を TUI 用に切り替え、終了・エラー・panic のどれで抜けても元に戻す
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> std::io::Result<Self> {
+        if let Err(e) = enter_terminal() {
+            restore_terminal();
+            return Err(e);
+        }
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+// raw モード中の Ctrl+C はキー入力として届くため、ここで待つのは外から送られたシグナル
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, Signal, SignalKind};
+    let mut interrupt: Signal = signal(SignalKind::interrupt())?;
+    let mut terminate: Signal = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = interrupt.recv() => {}
+        _ = terminate.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+// 端末を戻してから自分に SIGTSTP を送り、fg で再開されたら TUI の画面を作り直す
+#[cfg(unix)]
+fn suspend(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> std::io::Result<()> {
+    restore_terminal();
+    // SAFETY: 自プロセスにシグナルを送るだけで、メモリには触れない
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    enter_terminal()?;
+    terminal.clear()
+}
+
+#[cfg(not(unix))]
+fn suspend(_terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> std::io::Result<()> {
+    Ok(())
+}
+
+pub async fn run(target: &ServerProfile, friends: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    // 取得処理のエラー型は Send ではないため、同じスレッド上の LocalSet でタスクを動かす
+    tokio::task::LocalSet::new().run_until(run_app(target, friends)).await
+}
+
+async fn run_app(target: &ServerProfile, friends: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app: App<'_> = App::new(target, friends)?;
+
+    let _guard: TerminalGuard = TerminalGuard::enter()?;
+    let backend: CrosstermBackend<std::io::Stdout> = CrosstermBackend::new(std::io::stdout());
+    let mut terminal: Terminal<CrosstermBackend<std::io::Stdout>> = Terminal::new(backend)?;
+    terminal.clear()?;
+
+    let (control_tx, control_rx) = mpsc::unbounded_channel::<Control>();
+    let (poll_tx, mut poll_rx) = mpsc::unbounded_channel::<PollEvent>();
+    let poller: tokio::task::JoinHandle<()> = tokio::task::spawn_local(poll_loop(target.clone(), control_rx, poll_tx));
+    let mut input: mpsc::UnboundedReceiver<Event> = spawn_input();
+    let mut ticker: tokio::time::Interval = tokio::time::interval(FRAME_INTERVAL);
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        terminal.draw(|f: &mut Frame<'_>| app.draw(f))?;
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            Some(event) = poll_rx.recv() => app.apply(event),
+            Some(ev) = input.recv() => {
+                let Event::Key(key) = ev else {
+                    continue;
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if key.modifiers.contains(KeyModifiers::CONTROL) {
+                    match key.code {
+                        KeyCode::Char('c') => break,
+                        KeyCode::Char('z') => suspend(&mut terminal)?,
+                        _ => {}
+                    }
+                    continue;
+                }
+                if app.handle_players_key(key) {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') => break,
+                    KeyCode::Char('r') => {
+                        let _ = control_tx.send(Control::Refresh);
+                    }
+                    KeyCode::Char('p') => {
+                        let _ = control_tx.send(Control::TogglePause);
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') => app.zoom = app.zoom.zoom_in(),
+                    KeyCode::Char('-') => app.zoom = app.zoom.zoom_out(),
+                    KeyCode::Tab => app.set_tab(app.tab.next()),
+                    KeyCode::BackTab => app.set_tab(app.tab.prev()),
+                    KeyCode::Char(c @ '1'..='6') => app.set_tab(TABS[c as usize - '1' as usize]),
+                    _ => {}
+                }
+            }
+            _ = ticker.tick() => app.frame = app.frame.wrapping_add(1),
+        }
+    }
+
+    poller.abort();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Players;
+
+    #[test]
+    fn filters_and_sorts_players() {
+        let target: ServerProfile = ServerProfile::havenmc();
+        let friends: Vec<String> = vec!["carol".into()];
+        let samples: Vec<Sample> = vec![
+            Sample::at(1, 12, 0).with_players(&["alice"]),
+            Sample::at(1, 12, 1).with_players(&["alice", "Bob"]),
+            Sample::at(1, 12, 2).with_players(&["alice", "Bob", "carol"]),
+        ];
+        let mut app: App = App::from_history(&target, &friends, samples);
+        // zed は参加時刻が分からない
+        let list: Vec<String> = ["Bob", "alice", "carol", "zed"].iter().map(|p: &&str| p.to_string()).collect();
+        app.st = Some(HavenStatus { online: true, players: Players { online: 4, max: 20, list: Some(list) }, version: "1.21.4".into() });
+
+        assert_eq!(app.visible_players(), ["carol", "alice", "Bob", "zed"]);
+        app.sort = PlayerSort::Joined;
+        assert_eq!(app.visible_players(), ["carol", "Bob", "alice", "zed"]);
+        app.sort = PlayerSort::Session;
+        assert_eq!(app.visible_players(), ["carol", "alice", "Bob", "zed"]);
+
+        app.sort = PlayerSort::Name;
+        app.filter = "A".into();
+        assert_eq!(app.visible_players(), ["carol", "alice"]);
+    }
+
+    #[test]
+    fn leaving_the_tab_ends_filter_input() {
+        let target: ServerProfile = ServerProfile::havenmc();
+        let mut app: App = App::from_history(&target, &[], Vec::new());
+        let key = |code: KeyCode| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert!(app.handle_players_key(key(KeyCode::Char('/'))));
+        assert!(app.handle_players_key(key(KeyCode::Char('q'))));
+        assert_eq!(app.filter, "q");
+
+        app.set_tab(Tab::History);
+        assert!(!app.filtering);
+        // 別のタブでは q や数字キーを奪わない
+        assert!(!app.handle_players_key(key(KeyCode::Char('q'))));
+        assert!(!app.handle_players_key(key(KeyCode::Char('1'))));
+        assert_eq!(app.filter, "q");
+    }
+}